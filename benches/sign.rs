@@ -0,0 +1,61 @@
+//! 请求签名吞吐：每次签名解析PEM(旧实现) vs 使用构造时解析好的私钥
+//!
+//! ```shell
+//! cargo bench --bench sign
+//! ```
+use criterion::{criterion_group, criterion_main, Criterion};
+use rsa::pkcs8::{DecodePrivateKey, EncodePrivateKey, LineEnding};
+use rsa::rand_core::OsRng;
+use rsa::sha2::{Digest, Sha256};
+use rsa::{Pkcs1v15Sign, RsaPrivateKey};
+use std::hint::black_box;
+use wechat_pay_rust_sdk::pay::WechatPay;
+
+const MESSAGE: &str = "POST\n/v3/pay/transactions/native\n1705066163\n593BEC0C930BF1AFEB40B4A08C8FB242\n{\"appid\":\"wxd678efh567hg6787\",\"mchid\":\"1230000109\",\"description\":\"测试支付1分\",\"out_trade_no\":\"1217752501201407033233368018\",\"amount\":{\"total\":1}}\n";
+
+fn sign_benchmark(c: &mut Criterion) {
+    let private_key = RsaPrivateKey::new(&mut OsRng, 2048)
+        .unwrap()
+        .to_pkcs8_pem(LineEnding::LF)
+        .unwrap();
+    let wechat_pay = WechatPay::try_new(
+        "wxd678efh567hg6787",
+        "1230000109",
+        private_key.as_str(),
+        "MERCHANT_SERIAL",
+        "0123456789abcdef0123456789abcdef",
+        "https://mydomain.com/pay/notify",
+    )
+    .unwrap();
+
+    let mut group = c.benchmark_group("rsa_sign");
+    group.bench_function("parse_pem_per_call", |b| {
+        b.iter(|| {
+            let key = RsaPrivateKey::from_pkcs8_pem(black_box(private_key.as_str())).unwrap();
+            let hashed = Sha256::new().chain_update(black_box(MESSAGE)).finalize();
+            key.sign(Pkcs1v15Sign::new::<Sha256>(), &hashed).unwrap()
+        })
+    });
+    group.bench_function("cached_key", |b| {
+        b.iter(|| rsa_sign(&wechat_pay, black_box(MESSAGE)))
+    });
+    group.finish();
+}
+
+#[cfg(not(feature = "async"))]
+fn rsa_sign(wechat_pay: &WechatPay, message: &str) -> String {
+    wechat_pay.rsa_sign(message).unwrap()
+}
+
+#[cfg(feature = "async")]
+fn rsa_sign(wechat_pay: &WechatPay, message: &str) -> String {
+    static RUNTIME: std::sync::LazyLock<tokio::runtime::Runtime> = std::sync::LazyLock::new(|| {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+    });
+    RUNTIME.block_on(wechat_pay.rsa_sign(message)).unwrap()
+}
+
+criterion_group!(benches, sign_benchmark);
+criterion_main!(benches);
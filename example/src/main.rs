@@ -1,8 +1,12 @@
+use actix_web::web::Data;
 use actix_web::web::{Bytes, Json};
 use actix_web::{get, post, App, HttpRequest, HttpResponse, HttpServer, Responder};
 use dotenvy::dotenv;
 use tracing::debug;
-use wechat_pay_rust_sdk::model::{H5Params, H5SceneInfo, WechatPayDecodeData, WechatPayNotify};
+use wechat_pay_rust_sdk::integration::VerifiedNotification;
+use wechat_pay_rust_sdk::model::{
+    H5Params, H5SceneInfo, NotifyReply, WechatPayDecodeData, WechatPayNotify,
+};
 use wechat_pay_rust_sdk::pay::{PayNotifyTrait, WechatPay};
 use wechat_pay_rust_sdk::util;
 
@@ -36,7 +40,7 @@ async fn pay_notify3(bytes: Bytes, req: HttpRequest) -> impl Responder {
         .unwrap();
     let wechatpay_nonce = headers.get("wechatpay-nonce").unwrap().to_str().unwrap();
     let body = String::from_utf8(bytes.to_vec()).unwrap();
-    let wechat_pay = WechatPay::from_env();
+    let wechat_pay = WechatPay::from_env().unwrap();
     let _ = wechat_pay
         .h5_pay(H5Params::new(
             "测试支付1分",
@@ -62,6 +66,13 @@ async fn pay_notify3(bytes: Bytes, req: HttpRequest) -> impl Responder {
     }))
 }
 
+#[post("/pay/notify4")]
+async fn pay_notify4(notification: VerifiedNotification<WechatPayDecodeData>) -> NotifyReply {
+    debug!("event_type: {}", notification.notify.event_type);
+    debug!("data: {:#?}", notification.data);
+    NotifyReply::success()
+}
+
 #[get("/")]
 async fn home() -> impl Responder {
     HttpResponse::Ok().body("hello rust")
@@ -84,7 +95,7 @@ async fn pay_notify2(
     let ciphertext = data.resource.ciphertext;
     let associated_data = data.resource.associated_data.unwrap_or_default();
     dotenv().ok();
-    let wechat_pay = WechatPay::from_env();
+    let wechat_pay = WechatPay::from_env().unwrap();
     let result: WechatPayDecodeData = wechat_pay
         .decrypt_paydata(ciphertext, nonce, associated_data)
         .unwrap();
@@ -102,9 +113,19 @@ async fn main() -> std::io::Result<()> {
         .with_line_number(true)
         .init();
 
+    dotenv().ok();
+    let pub_key = std::fs::read_to_string("pubkey.pem").unwrap();
+    let serial_no = std::env::var("WECHAT_PLATFORM_SERIAL_NO").unwrap();
+    let wechat_pay = Data::new(
+        WechatPay::from_env()
+            .unwrap()
+            .with_platform_pub_key(serial_no, pub_key),
+    );
     HttpServer::new(move || {
         App::new()
+            .app_data(wechat_pay.clone())
             .service(pay_notify)
+            .service(pay_notify4)
             .service(pay_notify2)
             .service(home)
     })
@@ -1,30 +1,40 @@
-use crate::debug;
-use crate::error::PayError;
+use crate::bill::{self, FundFlowBill, TradeBill};
+use crate::error::{PayError, WechatApiError};
+use crate::model::AbnormalRefundParams;
+use crate::model::AccountType;
 use crate::model::AppParams;
+use crate::model::BillType;
 use crate::model::H5Params;
 use crate::model::JsapiParams;
 use crate::model::MicroParams;
 use crate::model::NativeParams;
 use crate::model::ParamsTrait;
 use crate::model::RefundsParams;
-use crate::pay::{WechatPay, WechatPayTrait};
+use crate::model::TarType;
+use crate::pay::{
+    PayNotifyTrait, WechatPay, WechatPayTrait, API_DOMAIN, BACKUP_API_DOMAIN, PUB_KEY_ID_PREFIX,
+    WECHATPAY_SERIAL, WECHATPAY_SIGNATURE,
+};
 use crate::request::HttpMethod;
 use crate::response::AppResponse;
+use crate::response::BillResponse;
 use crate::response::H5Response;
 use crate::response::JsapiResponse;
 use crate::response::MicroResponse;
 use crate::response::RefundsResponse;
 use crate::response::ResponseTrait;
+use crate::response::SignData;
+use crate::response::TransactionResponse;
 use crate::response::WeChatResponse;
 use crate::response::{CertificateResponse, NativeResponse};
-use reqwest::header::{HeaderMap, REFERER};
+use crate::sensitive;
+use crate::transport::HttpRequest;
+use crate::{debug, retry};
+use http::header::{HeaderMap, HeaderValue, REFERER};
+use http::StatusCode;
+use serde::de::DeserializeOwned;
 use serde_json::{Map, Value};
 
-#[cfg(not(feature = "async"))]
-use reqwest::blocking::Client;
-#[cfg(feature = "async")]
-use reqwest::Client;
-
 #[cfg(feature = "async")]
 use maybe_async::maybe_async as maybe_async_attr;
 #[cfg(not(feature = "async"))]
@@ -45,44 +55,223 @@ impl WechatPay {
         map.insert("mchid".to_owned(), self.mch_id().into());
         map.insert("notify_url".to_owned(), self.notify_url().into());
         let body = serde_json::to_string(&map)?;
-        let headers = self.build_header(method.clone(), url, body.as_str())?;
-        let client = Client::new();
-        let url = format!("{}{}", self.base_url(), url);
-        debug!("url: {} body: {}", url, body);
-        let builder = match method {
-            HttpMethod::GET => client.get(url),
-            HttpMethod::POST => client.post(url),
-            HttpMethod::PUT => client.put(url),
-            HttpMethod::DELETE => client.delete(url),
-            HttpMethod::PATCH => client.patch(url),
-        };
-
-        builder
-            .headers(headers)
-            .body(body)
-            .send()
-            .await?
-            .json::<R>()
-            .await
-            .map(Ok)?
+        let (status, text) = self.send(method, url, body, HeaderMap::new()).await?;
+        parse_response(status, text.as_str())
     }
 
     #[maybe_async_attr]
     pub async fn get_pay<R: ResponseTrait>(&self, url: &str) -> Result<R, PayError> {
-        let body = "";
-        let headers = self.build_header(HttpMethod::GET, url, body)?;
-        let client = Client::new();
-        let url = format!("{}{}", self.base_url(), url);
+        let (status, text) = self
+            .send_idempotent(HttpMethod::GET, url, String::new(), HeaderMap::new())
+            .await?;
+        parse_response(status, text.as_str())
+    }
+
+    /// 签名并发送请求，校验应答签名后返回状态码与应答报文
+    #[maybe_async_attr]
+    async fn send(
+        &self,
+        method: HttpMethod,
+        url: &str,
+        body: String,
+        extra_headers: HeaderMap,
+    ) -> Result<(StatusCode, String), PayError> {
+        self.ensure_fresh_certificates().await?;
+        let (status, response_headers, text) = self
+            .send_unverified(method, url, body, extra_headers, false)
+            .await?;
+        self.verify_reply(status, &response_headers, text.as_str())
+            .await?;
+        Ok((status, text))
+    }
+
+    /// 配置了证书管理器时，在请求前按需下载平台证书
+    #[maybe_async_attr]
+    async fn ensure_fresh_certificates(&self) -> Result<(), PayError> {
+        if let Some(manager) = &self.certificate_manager {
+            manager.ensure_fresh(self).await?;
+        }
+        Ok(())
+    }
+
+    /// 2xx应答必须通过验签；非2xx应答只有带`Wechatpay-Signature`时才验签，
+    /// 网关直接返回的502、503等应答没有签名，按原样交给调用方解析为`WechatApiError`
+    #[maybe_async_attr]
+    async fn verify_reply(
+        &self,
+        status: StatusCode,
+        headers: &HeaderMap,
+        text: &str,
+    ) -> Result<(), PayError> {
+        if !status.is_success() && !headers.contains_key(WECHATPAY_SIGNATURE) {
+            debug!("unsigned error response {}, skip verify", status);
+            return Ok(());
+        }
+        if let Some(serial_no) = headers
+            .get(WECHATPAY_SERIAL)
+            .and_then(|value| value.to_str().ok())
+        {
+            self.fetch_platform_pub_key(serial_no).await?;
+        }
+        self.verify_response(headers, text)
+    }
+
+    /// 按`Wechatpay-Serial`获取平台公钥，平台证书序列号未知时由证书管理器重新下载
+    #[maybe_async_attr]
+    pub async fn fetch_platform_pub_key(
+        &self,
+        serial_no: &str,
+    ) -> Result<Option<String>, PayError> {
+        match &self.certificate_manager {
+            Some(manager)
+                if !serial_no.starts_with(PUB_KEY_ID_PREFIX)
+                    && !self.platform_pub_keys.contains_key(serial_no) =>
+            {
+                manager.public_key_or_refresh(self, serial_no).await
+            }
+            _ => Ok(self.platform_pub_key(serial_no)),
+        }
+    }
+
+    /// 使用商户签名器做SHA256 with RSA签名，返回base64编码的签名
+    #[maybe_async_attr]
+    pub async fn rsa_sign(&self, content: impl AsRef<str>) -> Result<String, PayError> {
+        self.signer()?.sign(content.as_ref()).await
+    }
+
+    /// 使用商户私钥解密应答中的敏感信息
+    #[maybe_async_attr]
+    pub async fn decrypt_sensitive(&self, ciphertext: impl AsRef<str>) -> Result<String, PayError> {
+        self.signer()?.decrypt(ciphertext.as_ref()).await
+    }
+
+    /// 调起支付的签名数据
+    #[maybe_async_attr]
+    pub async fn mut_sign_data<S>(&self, prefix: S, prepay_id: S) -> Result<SignData, PayError>
+    where
+        S: AsRef<str>,
+    {
+        let app_id = self.appid();
+        let now_time = self.now_timestamp();
+        let nonce_str = self.nonce_str();
+        let ext_str = format!(
+            "{prefix}{prepay_id}",
+            prefix = prefix.as_ref(),
+            prepay_id = prepay_id.as_ref()
+        );
+        let signed_str = self
+            .rsa_sign(format!("{app_id}\n{now_time}\n{nonce_str}\n{ext_str}\n"))
+            .await?;
+        Ok(SignData {
+            app_id,
+            sign_type: "RSA".into(),
+            package: ext_str,
+            nonce_str,
+            timestamp: now_time,
+            pay_sign: signed_str,
+        })
+    }
+
+    /// 按retry_policy重试幂等的请求，每次重试都会重新签名；按状态码与错误码决定是否重试，
+    /// 不再重试时才校验最后一次应答的签名
+    #[maybe_async_attr]
+    async fn send_idempotent(
+        &self,
+        method: HttpMethod,
+        url: &str,
+        body: String,
+        extra_headers: HeaderMap,
+    ) -> Result<(StatusCode, String), PayError> {
+        let mut attempt = 0;
+        loop {
+            self.ensure_fresh_certificates().await?;
+            let result = self
+                .send_unverified(
+                    method.clone(),
+                    url,
+                    body.clone(),
+                    extra_headers.clone(),
+                    true,
+                )
+                .await;
+            let retryable = match &result {
+                Ok((status, _, text)) => retry::is_retryable_response(*status, text.as_str()),
+                Err(e) => e.is_retryable(),
+            };
+            if !retryable || attempt >= self.retry_policy.max_retries {
+                let (status, response_headers, text) = result?;
+                self.verify_reply(status, &response_headers, text.as_str())
+                    .await?;
+                return Ok((status, text));
+            }
+            let delay = self.retry_policy.delay(attempt);
+            debug!("retry {} after {:?}: {}", url, delay, attempt);
+            retry::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// 签名并发送请求，不校验应答签名，返回状态码、应答头与应答报文
+    #[maybe_async_attr]
+    pub(crate) async fn send_unverified(
+        &self,
+        method: HttpMethod,
+        url: &str,
+        body: String,
+        extra_headers: HeaderMap,
+        idempotent: bool,
+    ) -> Result<(StatusCode, HeaderMap, String), PayError> {
+        let (status, response_headers, bytes) = self
+            .send_raw(method, url, body, extra_headers, idempotent)
+            .await?;
+        let text = String::from_utf8_lossy(bytes.as_slice()).into_owned();
+        debug!("status: {} response: {}", status, text);
+        Ok((status, response_headers, text))
+    }
+
+    /// 签名并发送请求，返回状态码、应答头与原始应答内容；
+    /// 配置了容灾时，idempotent为true的请求返回5xx也会切换域名重发
+    #[maybe_async_attr]
+    async fn send_raw(
+        &self,
+        method: HttpMethod,
+        url: &str,
+        body: String,
+        extra_headers: HeaderMap,
+        idempotent: bool,
+    ) -> Result<(StatusCode, HeaderMap, Vec<u8>), PayError> {
+        let mut request = self.sign_request(method, url, body, extra_headers).await?;
+        let transport = self.transport()?;
+        let response = match &self.failover {
+            Some(failover) => failover.send(transport, request, idempotent).await?,
+            None => {
+                request.url = format!("{}{}", self.base_url(), url);
+                transport.send(request).await?
+            }
+        };
+        Ok((response.status, response.headers, response.body))
+    }
+
+    /// 生成带签名请求头的请求，url为path与query
+    #[maybe_async_attr]
+    async fn sign_request(
+        &self,
+        method: HttpMethod,
+        url: &str,
+        body: String,
+        extra_headers: HeaderMap,
+    ) -> Result<HttpRequest, PayError> {
+        let mut headers = self
+            .build_header(method.clone(), url, body.as_str())
+            .await?;
+        headers.extend(extra_headers);
         debug!("url: {} body: {}", url, body);
-        client
-            .get(url)
-            .headers(headers)
-            .body(body)
-            .send()
-            .await?
-            .json::<R>()
-            .await
-            .map(Ok)?
+        Ok(HttpRequest {
+            method,
+            url: url.to_string(),
+            headers,
+            body: body.into_bytes(),
+        })
     }
 
     #[maybe_async_attr]
@@ -93,38 +282,29 @@ impl WechatPay {
     #[maybe_async_attr]
     pub async fn app_pay(&self, params: AppParams) -> Result<AppResponse, PayError> {
         let url = "/v3/pay/transactions/app";
-        self.pay(HttpMethod::POST, url, params)
-            .await
-            .map(|mut result: AppResponse| {
-                if let Some(prepay_id) = &result.prepay_id {
-                    result.sign_data = Some(self.mut_sign_data("", prepay_id));
-                }
-                result
-            })
+        let mut result: AppResponse = self.pay(HttpMethod::POST, url, params).await?;
+        if let Some(prepay_id) = &result.prepay_id {
+            result.sign_data = Some(self.mut_sign_data("", prepay_id).await?);
+        }
+        Ok(result)
     }
     #[maybe_async_attr]
     pub async fn jsapi_pay(&self, params: JsapiParams) -> Result<JsapiResponse, PayError> {
         let url = "/v3/pay/transactions/jsapi";
-        self.pay(HttpMethod::POST, url, params)
-            .await
-            .map(|mut result: JsapiResponse| {
-                if let Some(prepay_id) = &result.prepay_id {
-                    result.sign_data = Some(self.mut_sign_data("prepay_id=", prepay_id));
-                }
-                result
-            })
+        let mut result: JsapiResponse = self.pay(HttpMethod::POST, url, params).await?;
+        if let Some(prepay_id) = &result.prepay_id {
+            result.sign_data = Some(self.mut_sign_data("prepay_id=", prepay_id).await?);
+        }
+        Ok(result)
     }
     #[maybe_async_attr]
     pub async fn micro_pay(&self, params: MicroParams) -> Result<MicroResponse, PayError> {
         let url = "/v3/pay/transactions/jsapi";
-        self.pay(HttpMethod::POST, url, params)
-            .await
-            .map(|mut result: MicroResponse| {
-                if let Some(prepay_id) = &result.prepay_id {
-                    result.sign_data = Some(self.mut_sign_data("prepay_id=", prepay_id));
-                }
-                result
-            })
+        let mut result: MicroResponse = self.pay(HttpMethod::POST, url, params).await?;
+        if let Some(prepay_id) = &result.prepay_id {
+            result.sign_data = Some(self.mut_sign_data("prepay_id=", prepay_id).await?);
+        }
+        Ok(result)
     }
     #[maybe_async_attr]
     pub async fn native_pay(&self, params: NativeParams) -> Result<NativeResponse, PayError> {
@@ -132,6 +312,60 @@ impl WechatPay {
         self.pay(HttpMethod::POST, url, params).await
     }
 
+    #[maybe_async_attr]
+    pub async fn query_order_by_transaction_id<S>(
+        &self,
+        transaction_id: S,
+    ) -> Result<WeChatResponse<TransactionResponse>, PayError>
+    where
+        S: AsRef<str>,
+    {
+        let url = format!(
+            "/v3/pay/transactions/id/{}?mchid={}",
+            transaction_id.as_ref(),
+            self.mch_id()
+        );
+        self.get_pay(url.as_str()).await
+    }
+
+    #[maybe_async_attr]
+    pub async fn query_order_by_out_trade_no<S>(
+        &self,
+        out_trade_no: S,
+    ) -> Result<WeChatResponse<TransactionResponse>, PayError>
+    where
+        S: AsRef<str>,
+    {
+        let url = format!(
+            "/v3/pay/transactions/out-trade-no/{}?mchid={}",
+            out_trade_no.as_ref(),
+            self.mch_id()
+        );
+        self.get_pay(url.as_str()).await
+    }
+
+    /// 关闭订单，微信支付成功受理后返回 204 No Content，无响应体
+    #[maybe_async_attr]
+    pub async fn close_order<S>(&self, out_trade_no: S) -> Result<(), PayError>
+    where
+        S: AsRef<str>,
+    {
+        let url = format!(
+            "/v3/pay/transactions/out-trade-no/{}/close",
+            out_trade_no.as_ref()
+        );
+        let mut map: Map<String, Value> = Map::new();
+        map.insert("mchid".to_owned(), self.mch_id().into());
+        let body = serde_json::to_string(&map)?;
+        let (status, text) = self
+            .send_idempotent(HttpMethod::POST, url.as_str(), body, HeaderMap::new())
+            .await?;
+        if status.is_success() {
+            return Ok(());
+        }
+        Err(WechatApiError::from_body(status, text.as_bytes()).into())
+    }
+
     #[maybe_async_attr]
     pub async fn certificates(&self) -> Result<CertificateResponse, PayError> {
         let url = "/v3/certificates";
@@ -142,16 +376,16 @@ impl WechatPay {
     where
         S: AsRef<str>,
     {
-        let client = Client::new();
         let mut headers = HeaderMap::new();
-        headers.insert(REFERER, referer.as_ref().parse().unwrap());
-        let text = client
-            .get(h5_url.as_ref())
-            .headers(headers)
-            .send()
-            .await?
-            .text()
-            .await?;
+        headers.insert(REFERER, HeaderValue::from_str(referer.as_ref())?);
+        let request = HttpRequest {
+            method: HttpMethod::GET,
+            url: h5_url.as_ref().to_string(),
+            headers,
+            body: vec![],
+        };
+        let response = self.transport()?.send(request).await?;
+        let text = String::from_utf8_lossy(response.body.as_slice());
         text.split("\n")
             .find(|line| line.contains("weixin://"))
             .map(|line| {
@@ -159,7 +393,19 @@ impl WechatPay {
                     .find(|line| line.contains("weixin://"))
                     .map(|line| line.to_string())
             })
-            .ok_or_else(|| PayError::WeixinNotFound)
+            .ok_or(PayError::WeixinNotFound)
+    }
+
+    #[maybe_async_attr]
+    pub async fn query_refund<S>(
+        &self,
+        out_refund_no: S,
+    ) -> Result<WeChatResponse<RefundsResponse>, PayError>
+    where
+        S: AsRef<str>,
+    {
+        let url = format!("/v3/refund/domestic/refunds/{}", out_refund_no.as_ref());
+        self.get_pay(url.as_str()).await
     }
 
     #[maybe_async_attr]
@@ -169,40 +415,651 @@ impl WechatPay {
     ) -> Result<WeChatResponse<RefundsResponse>, PayError> {
         let url = "/v3/refund/domestic/refunds";
         let body = params.to_json();
-        let headers = self.build_header(HttpMethod::POST, url, body.as_str())?;
-        let client = Client::new();
-        let url = format!("{}{}", self.base_url(), url);
-        debug!("url: {} body: {}", url, body);
-        let builder = client.post(url);
-
-        builder
-            .headers(headers)
-            .body(body)
-            .send()
-            .await?
-            .json::<WeChatResponse<RefundsResponse>>()
-            .await
-            .map(Ok)?
+        // 相同商户退款单号的退款申请是幂等的
+        let (status, text) = self
+            .send_idempotent(HttpMethod::POST, url, body, HeaderMap::new())
+            .await?;
+        parse_response(status, text.as_str())
+    }
+
+    /// 申请交易账单，bill_date格式为yyyy-MM-dd，仅支持三个月内的账单
+    #[maybe_async_attr]
+    pub async fn apply_trade_bill<S>(
+        &self,
+        bill_date: S,
+        bill_type: BillType,
+        tar_type: Option<TarType>,
+    ) -> Result<WeChatResponse<BillResponse>, PayError>
+    where
+        S: AsRef<str>,
+    {
+        let mut url = format!(
+            "/v3/bill/tradebill?bill_date={}&bill_type={}",
+            bill_date.as_ref(),
+            bill_type
+        );
+        if let Some(tar_type) = tar_type {
+            url.push_str(format!("&tar_type={}", tar_type).as_str());
+        }
+        self.get_pay(url.as_str()).await
+    }
+
+    /// 下载账单，download_url、hash_type与hash_value取自申请账单的应答，
+    /// GZIP压缩的账单会自动解压，并按hash_value校验账单完整性，返回账单文本
+    ///
+    /// 下载地址必须是微信支付域名(或当前配置的接口域名)，请求直接发往该地址，签名使用其中的path与query
+    #[maybe_async_attr]
+    pub async fn download_bill<S>(
+        &self,
+        download_url: S,
+        hash_type: S,
+        hash_value: S,
+    ) -> Result<String, PayError>
+    where
+        S: AsRef<str>,
+    {
+        let download_url = download_url.as_ref();
+        let url = self.bill_download_path(download_url)?;
+        let mut request = self
+            .sign_request(HttpMethod::GET, url, String::new(), HeaderMap::new())
+            .await?;
+        request.url = download_url.to_string();
+        let response = self.transport()?.send(request).await?;
+        if !response.status.is_success() {
+            return Err(
+                WechatApiError::from_body(response.status, response.body.as_slice()).into(),
+            );
+        }
+        bill::decode_bill(
+            response.body.as_slice(),
+            hash_type.as_ref(),
+            hash_value.as_ref(),
+        )
+    }
+
+    /// 校验账单下载地址的域名，返回用于签名的path与query
+    fn bill_download_path<'a>(&self, download_url: &'a str) -> Result<&'a str, PayError> {
+        let invalid = || PayError::BillError(format!("invalid download url {}", download_url));
+        let (scheme, rest) = download_url.split_once("://").ok_or_else(invalid)?;
+        let index = rest.find('/').ok_or_else(invalid)?;
+        let origin = &download_url[..scheme.len() + 3 + index];
+        let base_url = self.base_url();
+        let trusted = [API_DOMAIN, BACKUP_API_DOMAIN, base_url.as_str()]
+            .into_iter()
+            .chain(
+                self.failover
+                    .iter()
+                    .flat_map(|failover| failover.domains().iter().map(String::as_str)),
+            )
+            .any(|domain| domain.eq_ignore_ascii_case(origin));
+        if !trusted {
+            return Err(PayError::BillError(format!(
+                "download url {} is not a wechat pay domain",
+                download_url
+            )));
+        }
+        Ok(&rest[index..])
+    }
+
+    /// 申请并下载交易账单，解析为明细与汇总
+    #[maybe_async_attr]
+    pub async fn download_trade_bill<S>(
+        &self,
+        bill_date: S,
+        bill_type: BillType,
+    ) -> Result<TradeBill, PayError>
+    where
+        S: AsRef<str>,
+    {
+        let response = self
+            .apply_trade_bill(bill_date, bill_type, Some(TarType::Gzip))
+            .await?;
+        let content = self.download_applied_bill(response).await?;
+        TradeBill::parse(content.as_str())
+    }
+
+    /// 申请资金账单，bill_date格式为yyyy-MM-dd，account_type不填默认为基本账户
+    #[maybe_async_attr]
+    pub async fn apply_fund_flow_bill<S>(
+        &self,
+        bill_date: S,
+        account_type: Option<AccountType>,
+        tar_type: Option<TarType>,
+    ) -> Result<WeChatResponse<BillResponse>, PayError>
+    where
+        S: AsRef<str>,
+    {
+        let mut url = format!("/v3/bill/fundflowbill?bill_date={}", bill_date.as_ref());
+        if let Some(account_type) = account_type {
+            url.push_str(format!("&account_type={}", account_type).as_str());
+        }
+        if let Some(tar_type) = tar_type {
+            url.push_str(format!("&tar_type={}", tar_type).as_str());
+        }
+        self.get_pay(url.as_str()).await
+    }
+
+    /// 申请并下载资金账单，解析为资金流水与汇总
+    #[maybe_async_attr]
+    pub async fn download_fund_flow_bill<S>(
+        &self,
+        bill_date: S,
+        account_type: AccountType,
+    ) -> Result<FundFlowBill, PayError>
+    where
+        S: AsRef<str>,
+    {
+        let response = self
+            .apply_fund_flow_bill(bill_date, Some(account_type), Some(TarType::Gzip))
+            .await?;
+        let content = self.download_applied_bill(response).await?;
+        FundFlowBill::parse(content.as_str())
+    }
+
+    #[maybe_async_attr]
+    async fn download_applied_bill(
+        &self,
+        response: WeChatResponse<BillResponse>,
+    ) -> Result<String, PayError> {
+        match response {
+            WeChatResponse::Ok(bill) => {
+                self.download_bill(
+                    bill.download_url.as_str(),
+                    bill.hash_type.as_str(),
+                    bill.hash_value.as_str(),
+                )
+                .await
+            }
+            WeChatResponse::Err(error) => Err(WechatApiError::new(StatusCode::OK, error).into()),
+        }
+    }
+
+    /// 发起异常退款，bank_account与real_name传入明文，使用`platform_encrypt_key`选择的平台公钥加密后上送
+    #[maybe_async_attr]
+    pub async fn apply_abnormal_refund<S>(
+        &self,
+        refund_id: S,
+        params: AbnormalRefundParams,
+    ) -> Result<WeChatResponse<RefundsResponse>, PayError>
+    where
+        S: AsRef<str>,
+    {
+        let (platform_serial, platform_pub_key) = self.platform_encrypt_key()?;
+        let url = format!(
+            "/v3/refund/domestic/refunds/{}/apply-abnormal-refund",
+            refund_id.as_ref()
+        );
+        let body = sensitive::to_json(&params, platform_pub_key.as_str())?;
+        let serial = HeaderValue::from_str(platform_serial.as_str())?;
+        let mut headers = HeaderMap::new();
+        headers.insert(WECHATPAY_SERIAL, serial);
+        let (status, text) = self
+            .send(HttpMethod::POST, url.as_str(), body, headers)
+            .await?;
+        parse_response(status, text.as_str())
     }
 }
 
+/// 2xx应答解析为R，其余应答解析为WechatApiError
+fn parse_response<R: DeserializeOwned>(status: StatusCode, text: &str) -> Result<R, PayError> {
+    if !status.is_success() {
+        return Err(WechatApiError::from_body(status, text.as_bytes()).into());
+    }
+    Ok(serde_json::from_str(text)?)
+}
+
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use dotenvy::dotenv;
-    use crate::model::{
-        AppParams, H5Params, H5SceneInfo, JsapiParams, MicroParams, NativeParams, RefundsParams,
-    };
-    use crate::pay::{PayNotifyTrait, WechatPay};
-    use crate::response::Certificate;
+    use crate::bill::tests::{gzip_bill, TRADE_BILL};
+    use crate::error::{PayError, WechatErrorCode};
+    use crate::failover::{Failover, FailoverEvent};
+    #[cfg(not(feature = "async"))]
+    use crate::model::{AppParams, H5Params, H5SceneInfo, JsapiParams, MicroParams};
+    use crate::model::{BillType, NativeParams, RefundsParams};
+    #[cfg(not(feature = "async"))]
+    use crate::pay::PayNotifyTrait;
+    use crate::pay::WechatPay;
+    #[cfg(not(feature = "async"))]
+    use crate::response::{Certificate, RefundStatus};
+    use crate::retry::RetryPolicy;
     use crate::util;
-    use std::io::Write;
+    use rsa::pkcs8::{EncodePrivateKey, EncodePublicKey, LineEnding};
+    use rsa::rand_core::OsRng;
+    use rsa::sha2::{Digest, Sha256};
+    use rsa::{Pkcs1v15Sign, RsaPrivateKey, RsaPublicKey};
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
     use tracing::debug;
 
+    /// 本地桩服务，按顺序返回预设的应答，返回服务地址
+    pub(crate) fn stub_server<T>(responses: Vec<T>) -> String
+    where
+        T: AsRef<[u8]> + Send + 'static,
+    {
+        let (listener, base_url) = stub_listener();
+        serve_stub(listener, responses);
+        base_url
+    }
+
+    /// 绑定本地端口，应答内容需要包含服务地址时先绑定再调用serve_stub
+    fn stub_listener() -> (TcpListener, String) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        (listener, base_url)
+    }
+
+    fn serve_stub<T>(listener: TcpListener, responses: Vec<T>)
+    where
+        T: AsRef<[u8]> + Send + 'static,
+    {
+        std::thread::spawn(move || {
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line == "\r\n" || line.is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':')
+                        && name.eq_ignore_ascii_case("content-length")
+                    {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                stream.write_all(response.as_ref()).unwrap();
+            }
+        });
+    }
+
+    /// 使用平台私钥对应答签名，生成完整的http应答
+    pub(crate) fn stub_response(platform_key: &RsaPrivateKey, status: u16, body: &str) -> String {
+        stub_serial_response(platform_key, "PLATFORM_SERIAL", status, body)
+    }
+
+    /// 同stub_response，Wechatpay-Serial为serial_no
+    pub(crate) fn stub_serial_response(
+        platform_key: &RsaPrivateKey,
+        serial_no: &str,
+        status: u16,
+        body: &str,
+    ) -> String {
+        let timestamp = chrono::Local::now().timestamp();
+        let nonce = "0A1B2C3D4E5F6A7B8C9D";
+        let message = format!("{}\n{}\n{}\n", timestamp, nonce, body);
+        let hashed = Sha256::new().chain_update(message).finalize();
+        let signature = platform_key
+            .sign(Pkcs1v15Sign::new::<Sha256>(), &hashed)
+            .unwrap();
+        format!(
+            "HTTP/1.1 {} STUB\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\nWechatpay-Serial: {}\r\nWechatpay-Timestamp: {}\r\nWechatpay-Nonce: {}\r\nWechatpay-Signature: {}\r\n\r\n{}",
+            status,
+            body.len(),
+            serial_no,
+            timestamp,
+            nonce,
+            util::base64_encode(signature),
+            body
+        )
+    }
+
+    /// 生成商户私钥与平台密钥，返回指向桩服务的WechatPay与平台私钥
+    pub(crate) fn stub_wechat_pay(base_url: &str, platform_key: &RsaPrivateKey) -> WechatPay {
+        let merchant_key = RsaPrivateKey::new(&mut OsRng, 1024).unwrap();
+        let merchant_key = merchant_key.to_pkcs8_pem(LineEnding::LF).unwrap();
+        let platform_pub_key = RsaPublicKey::from(platform_key)
+            .to_public_key_pem(LineEnding::LF)
+            .unwrap();
+        WechatPay::try_new(
+            "wxd678efh567hg6787",
+            "1900000100",
+            merchant_key.as_str(),
+            "MERCHANT_SERIAL",
+            "0123456789abcdef0123456789abcdef",
+            "https://mydomain.com/pay/notify",
+        )
+        .unwrap()
+        .with_base_url(base_url)
+        .with_platform_pub_key("PLATFORM_SERIAL", platform_pub_key.as_str())
+    }
+
+    /// 桩服务依次返回平台私钥签名的应答，返回服务地址
+    pub(crate) fn signed_stub_server(
+        platform_key: &RsaPrivateKey,
+        responses: &[(u16, &str)],
+    ) -> String {
+        stub_server(
+            responses
+                .iter()
+                .map(|(status, body)| stub_response(platform_key, *status, body))
+                .collect(),
+        )
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_verify_response() {
+        let platform_key = RsaPrivateKey::new(&mut OsRng, 1024).unwrap();
+        let forged_key = RsaPrivateKey::new(&mut OsRng, 1024).unwrap();
+        let body = r#"{"code_url":"weixin://wxpay/bizpayurl?pr=p4lpSuKzz"}"#;
+        let base_url = stub_server(vec![
+            stub_response(&platform_key, 200, body),
+            stub_response(&forged_key, 200, body),
+            stub_response(&platform_key, 204, ""),
+        ]);
+        let wechat_pay = stub_wechat_pay(base_url.as_str(), &platform_key);
+        let params = NativeParams::new("测试支付1分", "1243243", 1.into());
+        let response = wechat_pay.native_pay(params.clone()).await.unwrap();
+        assert_eq!(
+            response.code_url.as_deref(),
+            Some("weixin://wxpay/bizpayurl?pr=p4lpSuKzz")
+        );
+        let error = wechat_pay.native_pay(params).await.unwrap_err();
+        assert!(matches!(error, PayError::SignatureError(_)));
+        wechat_pay.close_order("1243243").await.unwrap();
+    }
+
+    /// 桩服务依次返回申请账单应答与GZIP账单文件，下载地址指向桩服务
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_download_trade_bill() {
+        let platform_key = RsaPrivateKey::new(&mut OsRng, 1024).unwrap();
+        let (gzip, hash_value) = gzip_bill(TRADE_BILL);
+        let (listener, base_url) = stub_listener();
+        let apply = format!(
+            r#"{{"hash_type":"SHA1","hash_value":"{}","download_url":"{}/v3/billdownload/file?token=6XIv5TUPto7pByrTQKhd6kwvyKLG2uY2wMMR8cNXqaA_Cv_isgaUtBzp4QtiozLO"}}"#,
+            hash_value, base_url
+        );
+        let mut download = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            gzip.len()
+        )
+        .into_bytes();
+        download.extend(gzip);
+        serve_stub(
+            listener,
+            vec![
+                stub_response(&platform_key, 200, apply.as_str()).into_bytes(),
+                download,
+            ],
+        );
+        let wechat_pay = stub_wechat_pay(base_url.as_str(), &platform_key);
+        let bill = wechat_pay
+            .download_trade_bill("2024-01-02", BillType::All)
+            .await
+            .unwrap();
+        assert_eq!(bill.rows.len(), 2);
+        assert_eq!(bill.summary.total_count, 2);
+        // 非微信支付域名的下载地址不会发送请求
+        let error = wechat_pay
+            .download_bill(
+                "https://example.com/v3/billdownload/file?token=1",
+                "SHA1",
+                "",
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(error, PayError::BillError(_)));
+    }
+
+    const TRANSACTION: &str = r#"{"appid":"wxd678efh567hg6787","mchid":"1900000100","out_trade_no":"1243243","trade_state":"NOTPAY","trade_state_desc":"订单未支付"}"#;
+
+    /// 重试间隔为1ms
+    fn retry_policy() -> RetryPolicy {
+        RetryPolicy::default()
+            .with_max_retries(2)
+            .with_base_delay(Duration::from_millis(1))
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_retry_idempotent() {
+        let platform_key = RsaPrivateKey::new(&mut OsRng, 1024).unwrap();
+        let base_url = signed_stub_server(
+            &platform_key,
+            &[
+                (500, r#"{"code":"SYSTEM_ERROR","message":"系统错误"}"#),
+                (429, r#"{"code":"FREQUENCY_LIMITED","message":"频率超限"}"#),
+                (200, TRANSACTION),
+            ],
+        );
+        let wechat_pay =
+            stub_wechat_pay(base_url.as_str(), &platform_key).with_retry_policy(retry_policy());
+        let response = wechat_pay
+            .query_order_by_out_trade_no("1243243")
+            .await
+            .unwrap();
+        assert_eq!(response.ok().unwrap().trade_state, "NOTPAY");
+
+        let base_url = signed_stub_server(
+            &platform_key,
+            &[(400, r#"{"code":"PARAM_ERROR","message":"参数错误"}"#)],
+        );
+        let wechat_pay =
+            stub_wechat_pay(base_url.as_str(), &platform_key).with_retry_policy(retry_policy());
+        let error = wechat_pay.close_order("1243243").await.unwrap_err();
+        assert_eq!(
+            error.api_error().map(|e| &e.code),
+            Some(&WechatErrorCode::ParamError)
+        );
+
+        let base_url = signed_stub_server(
+            &platform_key,
+            &[
+                (500, r#"{"code":"SYSTEM_ERROR","message":"系统错误"}"#),
+                (500, r#"{"code":"SYSTEM_ERROR","message":"系统错误"}"#),
+                (500, r#"{"code":"SYSTEM_ERROR","message":"系统错误"}"#),
+                (204, ""),
+            ],
+        );
+        let wechat_pay =
+            stub_wechat_pay(base_url.as_str(), &platform_key).with_retry_policy(retry_policy());
+        let result = wechat_pay.close_order("1243243").await;
+        assert!(result.is_err());
+    }
+
+    /// 网关直接返回的应答，不带`Wechatpay-*`签名头
+    fn unsigned_response(status: u16, body: &str) -> String {
+        format!(
+            "HTTP/1.1 {} STUB\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_retry_unsigned_error() {
+        let platform_key = RsaPrivateKey::new(&mut OsRng, 1024).unwrap();
+        let base_url = stub_server(vec![
+            unsigned_response(502, "Bad Gateway"),
+            unsigned_response(503, "Service Unavailable"),
+            stub_response(&platform_key, 200, TRANSACTION),
+        ]);
+        let wechat_pay =
+            stub_wechat_pay(base_url.as_str(), &platform_key).with_retry_policy(retry_policy());
+        let response = wechat_pay
+            .query_order_by_out_trade_no("1243243")
+            .await
+            .unwrap();
+        assert_eq!(response.ok().unwrap().trade_state, "NOTPAY");
+
+        // 重试用尽后返回网关的502，而不是验签错误
+        let base_url = stub_server(vec![
+            unsigned_response(502, "Bad Gateway"),
+            unsigned_response(502, "Bad Gateway"),
+            unsigned_response(502, "Bad Gateway"),
+        ]);
+        let wechat_pay =
+            stub_wechat_pay(base_url.as_str(), &platform_key).with_retry_policy(retry_policy());
+        let error = wechat_pay.close_order("1243243").await.unwrap_err();
+        assert_eq!(
+            error.api_error().map(|e| e.status),
+            Some(http::StatusCode::BAD_GATEWAY)
+        );
+
+        // 2xx应答没有签名时验签失败
+        let base_url = stub_server(vec![unsigned_response(200, TRANSACTION)]);
+        let wechat_pay =
+            stub_wechat_pay(base_url.as_str(), &platform_key).with_retry_policy(retry_policy());
+        let error = wechat_pay
+            .query_order_by_out_trade_no("1243243")
+            .await
+            .unwrap_err();
+        assert!(matches!(error, PayError::SignatureError(_)));
+    }
+
+    /// 主域名与备用域名各一个桩服务，记录容灾事件
+    fn failover_stub(
+        platform_key: &RsaPrivateKey,
+        primary: &str,
+        backup: &[(u16, &str)],
+        failover: Failover,
+    ) -> (WechatPay, String, Arc<Mutex<Vec<String>>>) {
+        let backup = signed_stub_server(platform_key, backup);
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorder = events.clone();
+        let failover = Failover::new(primary, backup.as_str())
+            .with_failure_threshold(failover.failure_threshold)
+            .with_open_duration(failover.open_duration)
+            .with_listener(move |event| {
+                recorder.lock().unwrap().push(match event {
+                    FailoverEvent::Served { domain, .. } => format!("served {}", domain),
+                    FailoverEvent::Switched { from, to, .. } => format!("switched {} {}", from, to),
+                    FailoverEvent::CircuitOpened { domain } => format!("opened {}", domain),
+                    FailoverEvent::CircuitClosed { domain } => format!("closed {}", domain),
+                });
+            });
+        let wechat_pay = stub_wechat_pay(primary, platform_key).with_failover(failover);
+        (wechat_pay, backup, events)
+    }
+
+    /// 已关闭的本地端口，连接会被拒绝
+    fn closed_port() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("http://{}", listener.local_addr().unwrap())
+    }
+
+    fn take_events(events: &Mutex<Vec<String>>) -> Vec<String> {
+        std::mem::take(&mut *events.lock().unwrap())
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_failover_non_idempotent() {
+        let platform_key = RsaPrivateKey::new(&mut OsRng, 1024).unwrap();
+        let params = NativeParams::new("测试支付1分", "1243243", 1.into());
+        let body = r#"{"code_url":"weixin://wxpay/bizpayurl?pr=p4lpSuKzz"}"#;
+        // 5xx时下单请求可能已被处理，不切换域名重发
+        let primary = signed_stub_server(
+            &platform_key,
+            &[(500, r#"{"code":"SYSTEM_ERROR","message":"系统错误"}"#)],
+        );
+        let (wechat_pay, _, events) = failover_stub(
+            &platform_key,
+            primary.as_str(),
+            &[(200, body)],
+            Failover::default(),
+        );
+        let error = wechat_pay.native_pay(params.clone()).await.unwrap_err();
+        assert_eq!(
+            error.api_error().map(|e| e.status),
+            Some(http::StatusCode::INTERNAL_SERVER_ERROR)
+        );
+        assert_eq!(take_events(&events), [format!("served {}", primary)]);
+
+        // 连接失败时请求未送达，可以切换域名
+        let primary = closed_port();
+        let (wechat_pay, backup, events) = failover_stub(
+            &platform_key,
+            primary.as_str(),
+            &[(200, body)],
+            Failover::default(),
+        );
+        wechat_pay.native_pay(params).await.unwrap();
+        assert_eq!(
+            take_events(&events),
+            [
+                format!("switched {} {}", primary, backup),
+                format!("served {}", backup),
+            ]
+        );
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_failover() {
+        // 主域名连接失败，连续失败2次后熔断，之后直接请求备用域名
+        let platform_key = RsaPrivateKey::new(&mut OsRng, 1024).unwrap();
+        let primary = closed_port();
+        let (wechat_pay, backup, events) = failover_stub(
+            &platform_key,
+            primary.as_str(),
+            &[(200, TRANSACTION), (200, TRANSACTION), (204, "")],
+            Failover::default().with_failure_threshold(2),
+        );
+        let switched = format!("switched {} {}", primary, backup);
+        let served = format!("served {}", backup);
+        wechat_pay
+            .query_order_by_out_trade_no("1243243")
+            .await
+            .unwrap();
+        assert_eq!(take_events(&events), [switched.clone(), served.clone()]);
+        wechat_pay
+            .query_order_by_out_trade_no("1243243")
+            .await
+            .unwrap();
+        assert_eq!(
+            take_events(&events),
+            [format!("opened {}", primary), switched, served.clone()]
+        );
+        assert_eq!(
+            wechat_pay.failover.as_ref().unwrap().current_domain(),
+            backup
+        );
+        wechat_pay.close_order("1243243").await.unwrap();
+        assert_eq!(take_events(&events), [served]);
+
+        // 主域名先返回5xx后恢复，熔断时长为0，下一次请求即试探主域名
+        let primary = signed_stub_server(
+            &platform_key,
+            &[
+                (500, r#"{"code":"SYSTEM_ERROR","message":"系统错误"}"#),
+                (204, ""),
+            ],
+        );
+        let (wechat_pay, backup, events) = failover_stub(
+            &platform_key,
+            primary.as_str(),
+            &[(204, "")],
+            Failover::default()
+                .with_failure_threshold(1)
+                .with_open_duration(Duration::ZERO),
+        );
+        wechat_pay.close_order("1243243").await.unwrap();
+        assert_eq!(
+            take_events(&events),
+            [
+                format!("opened {}", primary),
+                format!("switched {} {}", primary, backup),
+                format!("served {}", backup),
+            ]
+        );
+        wechat_pay.close_order("1243243").await.unwrap();
+        assert_eq!(
+            take_events(&events),
+            [format!("closed {}", primary), format!("served {}", primary)]
+        );
+    }
+
     #[test]
+    #[cfg(not(feature = "async"))]
     pub fn test_jsapi_pay() {
         init_log();
         dotenv().ok();
-        let wechat_pay = WechatPay::from_env();
+        let wechat_pay = WechatPay::from_env().unwrap();
         let body = wechat_pay
             .jsapi_pay(JsapiParams::new(
                 "测试支付1分",
@@ -215,10 +1072,11 @@ mod tests {
     }
 
     #[test]
+    #[cfg(not(feature = "async"))]
     pub fn test_micro_pay() {
         init_log();
         dotenv().ok();
-        let wechat_pay = WechatPay::from_env();
+        let wechat_pay = WechatPay::from_env().unwrap();
         let body = wechat_pay
             .micro_pay(MicroParams::new(
                 "测试支付1分",
@@ -231,10 +1089,11 @@ mod tests {
     }
 
     #[test]
+    #[cfg(not(feature = "async"))]
     pub fn test_app_pay() {
         init_log();
         dotenv().ok();
-        let wechat_pay = WechatPay::from_env();
+        let wechat_pay = WechatPay::from_env().unwrap();
         let body = wechat_pay
             .app_pay(AppParams::new("测试支付1分", "1243243", 1.into()))
             .expect("app_pay error");
@@ -248,10 +1107,11 @@ mod tests {
     }
 
     #[test]
+    #[cfg(not(feature = "async"))]
     pub fn test_h5_pay() {
         init_log();
         dotenv().ok();
-        let wechat_pay = WechatPay::from_env();
+        let wechat_pay = WechatPay::from_env().unwrap();
         let body = wechat_pay
             .h5_pay(H5Params::new(
                 "测试支付1分",
@@ -267,10 +1127,11 @@ mod tests {
     }
 
     #[test]
+    #[cfg(not(feature = "async"))]
     pub fn test_certificates() {
         init_log();
         dotenv().ok();
-        let wechat_pay = WechatPay::from_env();
+        let wechat_pay = WechatPay::from_env().unwrap();
         let response = wechat_pay.certificates().expect("certificates error");
         let data = response.data.unwrap().first().unwrap().clone();
         let ciphertext = data.encrypt_certificate.ciphertext;
@@ -292,10 +1153,11 @@ mod tests {
     }
 
     #[test]
+    #[cfg(not(feature = "async"))]
     pub fn test_decode_certificates() {
         init_log();
         dotenv().ok();
-        let wechat_pay = WechatPay::from_env();
+        let wechat_pay = WechatPay::from_env().unwrap();
         let response = wechat_pay.certificates().expect("certificates error");
         let data: Certificate = response.data.unwrap()[0].clone();
         let ciphertext = data.encrypt_certificate.ciphertext;
@@ -308,10 +1170,11 @@ mod tests {
     }
 
     #[test]
+    #[cfg(not(feature = "async"))]
     pub fn test_blocking_refunds() {
         init_log();
         dotenv().ok();
-        let wechat_pay = WechatPay::from_env();
+        let wechat_pay = WechatPay::from_env().unwrap();
 
         let req = RefundsParams::new("123456", 1, 1, None, Some("123456"));
 
@@ -324,6 +1187,47 @@ mod tests {
         }
     }
 
+    #[test]
+    #[cfg(not(feature = "async"))]
+    pub fn test_query_order() {
+        init_log();
+        dotenv().ok();
+        let wechat_pay = WechatPay::from_env().unwrap();
+        let body = wechat_pay
+            .query_order_by_out_trade_no("1243243")
+            .expect("query_order_by_out_trade_no fail");
+        if body.is_success() {
+            debug!("query order success: {:?}", body.ok());
+        } else {
+            debug!("query order error: {:?}", body.err());
+        }
+    }
+
+    #[test]
+    #[cfg(not(feature = "async"))]
+    pub fn test_close_order() {
+        init_log();
+        dotenv().ok();
+        let wechat_pay = WechatPay::from_env().unwrap();
+        wechat_pay.close_order("1243243").expect("close_order fail");
+    }
+
+    #[test]
+    #[cfg(not(feature = "async"))]
+    pub fn test_query_refund() {
+        init_log();
+        dotenv().ok();
+        let wechat_pay = WechatPay::from_env().unwrap();
+        let body = wechat_pay
+            .query_refund("123456")
+            .expect("query_refund fail");
+        match body.ok().map(|refund| &refund.status) {
+            Some(RefundStatus::Success) => debug!("refund success: {:?}", body.ok()),
+            Some(status) => debug!("refund status: {}", status),
+            None => debug!("query refund error: {:?}", body.err()),
+        }
+    }
+
     #[inline]
     fn init_log() {
         tracing_subscriber::fmt()
@@ -337,7 +1241,7 @@ mod tests {
     pub async fn test_native_pay() {
         init_log();
         dotenv().ok();
-        let wechat_pay = WechatPay::from_env();
+        let wechat_pay = WechatPay::from_env().unwrap();
         let body = wechat_pay
             .native_pay(NativeParams::new("测试支付1分", "1243243", 1.into()))
             .await
@@ -349,7 +1253,7 @@ mod tests {
     pub fn test_native_pay() {
         init_log();
         dotenv().ok();
-        let wechat_pay = WechatPay::from_env();
+        let wechat_pay = WechatPay::from_env().unwrap();
         let body = wechat_pay
             .native_pay(NativeParams::new("测试支付1分", "1243243", 1.into()))
             .expect("pay fail");
@@ -361,7 +1265,7 @@ mod tests {
     pub async fn test_refunds() {
         init_log();
         dotenv().ok();
-        let wechat_pay = WechatPay::from_env();
+        let wechat_pay = WechatPay::from_env().unwrap();
 
         let req = RefundsParams::new("123456", 1, 1, None, Some("123456"));
 
@@ -379,7 +1283,7 @@ mod tests {
     pub fn test_refunds() {
         init_log();
         dotenv().ok();
-        let wechat_pay = WechatPay::from_env();
+        let wechat_pay = WechatPay::from_env().unwrap();
 
         let req = RefundsParams::new("123456", 1, 1, None, Some("123456"));
 
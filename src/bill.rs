@@ -0,0 +1,528 @@
+use crate::error::PayError;
+use flate2::read::GzDecoder;
+use rsa::sha2::Digest;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::io::Read;
+
+/// 交易账单中的一笔交易，金额单位为分，账单类型不包含的列为空字符串或0
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TradeBillRow {
+    /// 【交易时间】
+    pub trade_time: String,
+    /// 【公众账号ID】
+    pub appid: String,
+    /// 【商户号】
+    pub mch_id: String,
+    /// 【特约商户号】
+    pub sub_mch_id: String,
+    /// 【设备号】
+    pub device_info: String,
+    /// 【微信订单号】
+    pub transaction_id: String,
+    /// 【商户订单号】
+    pub out_trade_no: String,
+    /// 【用户标识】
+    pub openid: String,
+    /// 【交易类型】
+    pub trade_type: String,
+    /// 【交易状态】
+    pub trade_state: String,
+    /// 【付款银行】
+    pub bank_type: String,
+    /// 【货币种类】
+    pub currency: String,
+    /// 【应结订单金额】
+    pub settlement_total_fee: i64,
+    /// 【代金券金额】
+    pub coupon_fee: i64,
+    /// 【微信退款单号】
+    pub refund_id: String,
+    /// 【商户退款单号】
+    pub out_refund_no: String,
+    /// 【退款金额】
+    pub refund_fee: i64,
+    /// 【充值券退款金额】
+    pub coupon_refund_fee: i64,
+    /// 【退款类型】
+    pub refund_type: String,
+    /// 【退款状态】
+    pub refund_status: String,
+    /// 【商品名称】
+    pub body: String,
+    /// 【商户数据包】
+    pub attach: String,
+    /// 【手续费】
+    pub service_fee: i64,
+    /// 【费率】 如0.60%
+    pub rate: String,
+    /// 【订单金额】
+    pub total_fee: i64,
+    /// 【申请退款金额】
+    pub apply_refund_fee: i64,
+    /// 【费率备注】
+    pub rate_notes: String,
+}
+
+/// 交易账单汇总，金额单位为分
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TradeBillSummary {
+    /// 【总交易单数】
+    pub total_count: u64,
+    /// 【应结订单总金额】
+    pub settlement_total_fee: i64,
+    /// 【退款总金额】
+    pub refund_fee: i64,
+    /// 【充值券退款总金额】
+    pub coupon_refund_fee: i64,
+    /// 【手续费总金额】
+    pub service_fee: i64,
+    /// 【订单总金额】
+    pub total_fee: i64,
+    /// 【申请退款总金额】
+    pub apply_refund_fee: i64,
+}
+
+/// 交易账单
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TradeBill {
+    pub rows: Vec<TradeBillRow>,
+    pub summary: TradeBillSummary,
+}
+
+impl TradeBill {
+    /// 解析下载的交易账单，数据行与汇总行的每个字段以`` ` ``开头
+    pub fn parse(content: &str) -> Result<Self, PayError> {
+        let table = BillTable::parse(content, "总交易单数")?;
+        let rows = table
+            .rows()
+            .map(|row| {
+                Ok(TradeBillRow {
+                    trade_time: row.text("交易时间"),
+                    appid: row.text("公众账号ID"),
+                    mch_id: row.text("商户号"),
+                    sub_mch_id: row.text("特约商户号"),
+                    device_info: row.text("设备号"),
+                    transaction_id: row.text("微信订单号"),
+                    out_trade_no: row.text("商户订单号"),
+                    openid: row.text("用户标识"),
+                    trade_type: row.text("交易类型"),
+                    trade_state: row.text("交易状态"),
+                    bank_type: row.text("付款银行"),
+                    currency: row.text("货币种类"),
+                    settlement_total_fee: row.fen("应结订单金额")?,
+                    coupon_fee: row.fen("代金券金额")?,
+                    refund_id: row.text("微信退款单号"),
+                    out_refund_no: row.text("商户退款单号"),
+                    refund_fee: row.fen("退款金额")?,
+                    coupon_refund_fee: row.fen("充值券退款金额")?,
+                    refund_type: row.text("退款类型"),
+                    refund_status: row.text("退款状态"),
+                    body: row.text("商品名称"),
+                    attach: row.text("商户数据包"),
+                    service_fee: row.fen("手续费")?,
+                    rate: row.text("费率"),
+                    total_fee: row.fen("订单金额")?,
+                    apply_refund_fee: row.fen("申请退款金额")?,
+                    rate_notes: row.text("费率备注"),
+                })
+            })
+            .collect::<Result<Vec<_>, PayError>>()?;
+        let summary = table.summary();
+        let summary = TradeBillSummary {
+            total_count: summary.count("总交易单数")?,
+            settlement_total_fee: summary.fen("应结订单总金额")?,
+            refund_fee: summary.fen("退款总金额")?,
+            coupon_refund_fee: summary.fen("充值券退款总金额")?,
+            service_fee: summary.fen("手续费总金额")?,
+            total_fee: summary.fen("订单总金额")?,
+            apply_refund_fee: summary.fen("申请退款总金额")?,
+        };
+        Ok(Self { rows, summary })
+    }
+}
+
+/// 资金账单收支类型
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IncomeType {
+    /// 收入
+    Income,
+    /// 支出
+    Expense,
+    Unknown(String),
+}
+
+impl From<String> for IncomeType {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "收入" => IncomeType::Income,
+            "支出" => IncomeType::Expense,
+            _ => IncomeType::Unknown(value),
+        }
+    }
+}
+
+impl Display for IncomeType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IncomeType::Income => write!(f, "收入"),
+            IncomeType::Expense => write!(f, "支出"),
+            IncomeType::Unknown(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+/// 资金账单中的一笔资金流水，金额单位为分
+#[derive(Debug, Clone, PartialEq)]
+pub struct FundFlowRow {
+    /// 【记账时间】
+    pub accounting_time: String,
+    /// 【微信支付业务单号】
+    pub transaction_id: String,
+    /// 【资金流水单号】
+    pub fund_flow_id: String,
+    /// 【业务名称】
+    pub business_name: String,
+    /// 【业务类型】 如交易、退款、提现
+    pub business_type: String,
+    /// 【收支类型】
+    pub income_type: IncomeType,
+    /// 【收支金额】
+    pub amount: i64,
+    /// 【账户结余】
+    pub balance: i64,
+    /// 【资金变更提交申请人】
+    pub applicant: String,
+    /// 【备注】
+    pub remark: String,
+    /// 【业务凭证号】
+    pub voucher_no: String,
+}
+
+/// 资金账单汇总，金额单位为分
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FundFlowSummary {
+    /// 【资金流水总笔数】
+    pub total_count: u64,
+    /// 【收入笔数】
+    pub income_count: u64,
+    /// 【收入金额】
+    pub income_amount: i64,
+    /// 【支出笔数】
+    pub expense_count: u64,
+    /// 【支出金额】
+    pub expense_amount: i64,
+}
+
+/// 资金账单
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FundFlowBill {
+    pub rows: Vec<FundFlowRow>,
+    pub summary: FundFlowSummary,
+}
+
+impl FundFlowBill {
+    /// 解析下载的资金账单，数据行与汇总行的每个字段以`` ` ``开头
+    pub fn parse(content: &str) -> Result<Self, PayError> {
+        let table = BillTable::parse(content, "资金流水总笔数")?;
+        let rows = table
+            .rows()
+            .map(|row| {
+                Ok(FundFlowRow {
+                    accounting_time: row.text("记账时间"),
+                    transaction_id: row.text("微信支付业务单号"),
+                    fund_flow_id: row.text("资金流水单号"),
+                    business_name: row.text("业务名称"),
+                    business_type: row.text("业务类型"),
+                    income_type: row.text("收支类型").into(),
+                    amount: row.fen("收支金额（元）")?,
+                    balance: row.fen("账户结余（元）")?,
+                    applicant: row.text("资金变更提交申请人"),
+                    remark: row.text("备注"),
+                    voucher_no: row.text("业务凭证号"),
+                })
+            })
+            .collect::<Result<Vec<_>, PayError>>()?;
+        let summary = table.summary();
+        let summary = FundFlowSummary {
+            total_count: summary.count("资金流水总笔数")?,
+            income_count: summary.count("收入笔数")?,
+            income_amount: summary.fen("收入金额")?,
+            expense_count: summary.count("支出笔数")?,
+            expense_amount: summary.fen("支出金额")?,
+        };
+        Ok(Self { rows, summary })
+    }
+}
+
+/// 按表头取值的账单行
+#[derive(Debug)]
+pub(crate) struct BillRecord<'a> {
+    columns: &'a HashMap<&'a str, usize>,
+    values: Vec<&'a str>,
+}
+
+impl BillRecord<'_> {
+    pub(crate) fn text(&self, name: &str) -> String {
+        self.columns
+            .get(name)
+            .and_then(|index| self.values.get(*index))
+            .map(|value| value.trim().to_string())
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn fen(&self, name: &str) -> Result<i64, PayError> {
+        yuan_to_fen(self.text(name).as_str())
+            .ok_or_else(|| PayError::BillError(format!("invalid amount of {}", name)))
+    }
+
+    pub(crate) fn count(&self, name: &str) -> Result<u64, PayError> {
+        let value = self.text(name);
+        if value.is_empty() {
+            return Ok(0);
+        }
+        value
+            .parse()
+            .map_err(|_| PayError::BillError(format!("invalid count of {}", name)))
+    }
+}
+
+/// 账单表格，明细与汇总各自带有表头
+#[derive(Debug)]
+pub(crate) struct BillTable<'a> {
+    detail_columns: HashMap<&'a str, usize>,
+    detail_lines: Vec<&'a str>,
+    summary_columns: HashMap<&'a str, usize>,
+    summary_line: &'a str,
+}
+
+impl<'a> BillTable<'a> {
+    /// 以汇总表头首列(如`总交易单数`)拆分账单为明细与汇总两部分
+    pub(crate) fn parse(content: &'a str, summary_header: &str) -> Result<Self, PayError> {
+        let mut lines = content
+            .trim_start_matches('\u{feff}')
+            .lines()
+            .map(|line| line.trim_end_matches('\r'))
+            .filter(|line| !line.trim().is_empty());
+        let header = lines
+            .next()
+            .ok_or_else(|| PayError::BillError("empty bill".to_string()))?;
+        let mut detail_lines = vec![];
+        let mut summary = None;
+        for line in lines.by_ref() {
+            if line.starts_with(summary_header) {
+                summary = Some(line);
+                break;
+            }
+            detail_lines.push(line);
+        }
+        let summary_header =
+            summary.ok_or_else(|| PayError::BillError("bill summary not found".to_string()))?;
+        let summary_line = lines
+            .next()
+            .ok_or_else(|| PayError::BillError("bill summary not found".to_string()))?;
+        Ok(Self {
+            detail_columns: columns(header),
+            detail_lines,
+            summary_columns: columns(summary_header),
+            summary_line,
+        })
+    }
+
+    pub(crate) fn rows(&self) -> impl Iterator<Item = BillRecord<'_>> {
+        self.detail_lines.iter().map(|line| BillRecord {
+            columns: &self.detail_columns,
+            values: split_line(line),
+        })
+    }
+
+    pub(crate) fn summary(&self) -> BillRecord<'_> {
+        BillRecord {
+            columns: &self.summary_columns,
+            values: split_line(self.summary_line),
+        }
+    }
+}
+
+fn columns(header: &str) -> HashMap<&str, usize> {
+    header
+        .split(',')
+        .enumerate()
+        .map(|(index, name)| (name.trim(), index))
+        .collect()
+}
+
+/// 字段以`` ` ``开头，按`` ,` ``拆分以兼容字段中的逗号
+fn split_line(line: &str) -> Vec<&str> {
+    line.strip_prefix('`').unwrap_or(line).split(",`").collect()
+}
+
+/// 账单金额(元)转为分，空值为0
+pub(crate) fn yuan_to_fen(value: &str) -> Option<i64> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Some(0);
+    }
+    let (negative, value) = match value.strip_prefix('-') {
+        Some(value) => (true, value),
+        None => (false, value),
+    };
+    let (yuan, fen) = value.split_once('.').unwrap_or((value, ""));
+    if fen.len() > 2 || !fen.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let yuan: i64 = if yuan.is_empty() {
+        0
+    } else {
+        yuan.parse().ok()?
+    };
+    let fen: i64 = format!("{:0<2}", fen).parse().ok()?;
+    let amount = yuan * 100 + fen;
+    Some(if negative { -amount } else { amount })
+}
+
+/// 解压(gzip)下载的账单文件并校验摘要，返回账单文本
+pub(crate) fn decode_bill(
+    content: &[u8],
+    hash_type: &str,
+    hash_value: &str,
+) -> Result<String, PayError> {
+    let content = if content.starts_with(&[0x1f, 0x8b]) {
+        let mut decoded = vec![];
+        GzDecoder::new(content)
+            .read_to_end(&mut decoded)
+            .map_err(|e| PayError::BillError(format!("gzip decode error: {}", e)))?;
+        decoded
+    } else {
+        content.to_vec()
+    };
+    let digest = match hash_type {
+        "SHA1" => sha1::Sha1::digest(&content)
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>(),
+        _ => {
+            return Err(PayError::BillError(format!(
+                "unsupported hash type {}",
+                hash_type
+            )));
+        }
+    };
+    if !digest.eq_ignore_ascii_case(hash_value) {
+        return Err(PayError::BillError(format!(
+            "bill hash mismatch, expected {} got {}",
+            hash_value, digest
+        )));
+    }
+    String::from_utf8(content).map_err(|e| PayError::BillError(e.to_string()))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::bill::{decode_bill, yuan_to_fen, FundFlowBill, IncomeType, TradeBill};
+    use crate::error::PayError;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use rsa::sha2::Digest;
+    use std::io::Write;
+
+    pub(crate) const TRADE_BILL: &str = "\u{feff}交易时间,公众账号ID,商户号,特约商户号,设备号,微信订单号,商户订单号,用户标识,交易类型,交易状态,付款银行,货币种类,应结订单金额,代金券金额,微信退款单号,商户退款单号,退款金额,充值券退款金额,退款类型,退款状态,商品名称,商户数据包,手续费,费率,订单金额,申请退款金额,费率备注\r
+`2024-01-02 10:45:52,`wxd678efh567hg6787,`1900000100,`0,`,`4200001234202401021234567890,`20240102001,`oUpF8uMuAJO_M2pxb1Q9zNjWeS6o,`JSAPI,`SUCCESS,`CMC,`CNY,`1.00,`0.00,`0,`0,`0.00,`0.00,`,`,`测试商品,大号,`,`0.01,`0.60%,`1.00,`0.00,`\r
+`2024-01-02 11:00:00,`wxd678efh567hg6787,`1900000100,`0,`,`4200001234202401021234567891,`20240102002,`oUpF8uMuAJO_M2pxb1Q9zNjWeS6o,`JSAPI,`REFUND,`CMC,`CNY,`0.00,`0.00,`50300001234202401021234567890,`R20240102002,`0.50,`0.00,`ORIGINAL,`SUCCESS,`测试商品,`attach,`-0.00,`0.60%,`0.00,`0.50,`\r
+总交易单数,应结订单总金额,退款总金额,充值券退款总金额,手续费总金额,订单总金额,申请退款总金额\r
+`2,`1.00,`0.50,`0.00,`0.01,`1.00,`0.50\r
+";
+
+    const FUND_FLOW_BILL: &str = "记账时间,微信支付业务单号,资金流水单号,业务名称,业务类型,收支类型,收支金额（元）,账户结余（元）,资金变更提交申请人,备注,业务凭证号
+`2024-01-02 10:45:53,`4200001234202401021234567890,`4200001234202401021234567890,`测试商品,`交易,`收入,`1.00,`101.00,`system,`缺省,`REF4200001234202401021234567890
+`2024-01-02 11:00:01,`50300001234202401021234567890,`50300001234202401021234567890,`退款,`退款,`支出,`0.50,`100.50,`system,`缺省,`REF50300001234202401021234567890
+资金流水总笔数,收入笔数,收入金额,支出笔数,支出金额
+`2,`1,`1.00,`1,`0.50
+";
+
+    /// 生成GZIP账单与原始账单的SHA1摘要
+    pub(crate) fn gzip_bill(content: &str) -> (Vec<u8>, String) {
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(content.as_bytes()).unwrap();
+        let hash_value = sha1::Sha1::digest(content.as_bytes())
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        (encoder.finish().unwrap(), hash_value)
+    }
+
+    #[test]
+    fn test_parse_trade_bill() {
+        let bill = TradeBill::parse(TRADE_BILL).unwrap();
+        assert_eq!(bill.rows.len(), 2);
+        let payment = &bill.rows[0];
+        assert_eq!(payment.trade_time, "2024-01-02 10:45:52");
+        assert_eq!(payment.out_trade_no, "20240102001");
+        assert_eq!(payment.trade_state, "SUCCESS");
+        assert_eq!(payment.settlement_total_fee, 100);
+        assert_eq!(payment.service_fee, 1);
+        assert_eq!(payment.rate, "0.60%");
+        assert_eq!(payment.body, "测试商品,大号");
+        let refund = &bill.rows[1];
+        assert_eq!(refund.out_refund_no, "R20240102002");
+        assert_eq!(refund.refund_fee, 50);
+        assert_eq!(refund.refund_status, "SUCCESS");
+        assert_eq!(refund.attach, "attach");
+        assert_eq!(bill.summary.total_count, 2);
+        assert_eq!(bill.summary.settlement_total_fee, 100);
+        assert_eq!(bill.summary.refund_fee, 50);
+        assert_eq!(bill.summary.service_fee, 1);
+
+        assert!(matches!(
+            TradeBill::parse("交易时间,商户订单号\n`2024-01-02 10:45:52,`20240102001\n"),
+            Err(PayError::BillError(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_fund_flow_bill() {
+        let bill = FundFlowBill::parse(FUND_FLOW_BILL).unwrap();
+        assert_eq!(bill.rows.len(), 2);
+        let income = &bill.rows[0];
+        assert_eq!(income.accounting_time, "2024-01-02 10:45:53");
+        assert_eq!(income.business_type, "交易");
+        assert_eq!(income.income_type, IncomeType::Income);
+        assert_eq!(income.amount, 100);
+        assert_eq!(income.balance, 10100);
+        let expense = &bill.rows[1];
+        assert_eq!(expense.income_type, IncomeType::Expense);
+        assert_eq!(expense.amount, 50);
+        assert_eq!(expense.voucher_no, "REF50300001234202401021234567890");
+        assert_eq!(bill.summary.total_count, 2);
+        assert_eq!(bill.summary.income_count, 1);
+        assert_eq!(bill.summary.income_amount, 100);
+        assert_eq!(bill.summary.expense_count, 1);
+        assert_eq!(bill.summary.expense_amount, 50);
+    }
+
+    #[test]
+    fn test_yuan_to_fen() {
+        assert_eq!(yuan_to_fen("1.00"), Some(100));
+        assert_eq!(yuan_to_fen("0.5"), Some(50));
+        assert_eq!(yuan_to_fen("-0.01"), Some(-1));
+        assert_eq!(yuan_to_fen("12"), Some(1200));
+        assert_eq!(yuan_to_fen(""), Some(0));
+        assert_eq!(yuan_to_fen("0.001"), None);
+        assert_eq!(yuan_to_fen("abc"), None);
+    }
+
+    #[test]
+    fn test_decode_bill() {
+        let (gzip, hash_value) = gzip_bill(TRADE_BILL);
+        assert_eq!(
+            decode_bill(gzip.as_slice(), "SHA1", hash_value.as_str()).unwrap(),
+            TRADE_BILL
+        );
+        assert_eq!(
+            decode_bill(TRADE_BILL.as_bytes(), "SHA1", hash_value.as_str()).unwrap(),
+            TRADE_BILL
+        );
+        assert!(decode_bill(gzip.as_slice(), "SHA1", "0000").is_err());
+        assert!(decode_bill(gzip.as_slice(), "MD5", hash_value.as_str()).is_err());
+    }
+}
@@ -0,0 +1,705 @@
+//! 商户配置，支持代码、环境变量与TOML/JSON配置文件，`build`时一次性返回所有校验错误
+//!
+//! ```toml
+//! appid = "wxd678efh567hg6787"
+//! mch_id = "1230000109"
+//! serial_no = "MERCHANT_SERIAL"
+//! private_key_path = "apiclient_key.pem"
+//! v3_key = "0123456789abcdef0123456789abcdef"
+//! notify_url = "https://mydomain.com/pay/notify"
+//! base_url = "https://api2.mch.weixin.qq.com"
+//! connect_timeout_ms = 5000
+//! read_timeout_ms = 10000
+//! ```
+use crate::error::{ConfigError, PayError};
+use crate::failover::Failover;
+use crate::pay::{WechatPay, API_DOMAIN, BACKUP_API_DOMAIN};
+use crate::secret::{ApiV3Key, SecretString};
+use crate::signer::{RsaSigner, Signer};
+use crate::transport::HttpTransport;
+use crate::util;
+use rsa::pkcs8::DecodePublicKey;
+use rsa::RsaPublicKey;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 配置文件内容，所有字段可选，相对路径以配置文件所在目录为准
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct WechatPayConfig {
+    pub appid: Option<String>,
+    pub mch_id: Option<String>,
+    /// 商户证书序列号
+    pub serial_no: Option<String>,
+    /// 商户私钥PEM，与`private_key_path`二选一
+    pub private_key: Option<SecretString>,
+    pub private_key_path: Option<PathBuf>,
+    /// APIv3密钥，32字节
+    pub v3_key: Option<SecretString>,
+    /// 默认的回调地址
+    pub notify_url: Option<String>,
+    /// 接口域名，默认为`https://api.mch.weixin.qq.com`
+    pub base_url: Option<String>,
+    /// 微信支付公钥ID，形如`PUB_KEY_ID_...`
+    pub platform_public_key_id: Option<String>,
+    /// 微信支付公钥PEM，与`platform_public_key_path`二选一
+    pub platform_public_key: Option<String>,
+    pub platform_public_key_path: Option<PathBuf>,
+    /// 平台证书PEM，与`platform_certificate_path`二选一
+    pub platform_certificate: Option<String>,
+    pub platform_certificate_path: Option<PathBuf>,
+    pub connect_timeout_ms: Option<u64>,
+    pub read_timeout_ms: Option<u64>,
+}
+
+impl WechatPayConfig {
+    /// 使用other中已设置的字段覆盖当前配置，PEM与文件路径视为同一项
+    fn merge(self, other: WechatPayConfig) -> Self {
+        fn pair<A, B>(
+            current: (Option<A>, Option<B>),
+            other: (Option<A>, Option<B>),
+        ) -> (Option<A>, Option<B>) {
+            if other.0.is_some() || other.1.is_some() {
+                other
+            } else {
+                current
+            }
+        }
+        let (private_key, private_key_path) = pair(
+            (self.private_key, self.private_key_path),
+            (other.private_key, other.private_key_path),
+        );
+        let (platform_public_key, platform_public_key_path) = pair(
+            (self.platform_public_key, self.platform_public_key_path),
+            (other.platform_public_key, other.platform_public_key_path),
+        );
+        let (platform_certificate, platform_certificate_path) = pair(
+            (self.platform_certificate, self.platform_certificate_path),
+            (other.platform_certificate, other.platform_certificate_path),
+        );
+        Self {
+            appid: other.appid.or(self.appid),
+            mch_id: other.mch_id.or(self.mch_id),
+            serial_no: other.serial_no.or(self.serial_no),
+            private_key,
+            private_key_path,
+            v3_key: other.v3_key.or(self.v3_key),
+            notify_url: other.notify_url.or(self.notify_url),
+            base_url: other.base_url.or(self.base_url),
+            platform_public_key_id: other.platform_public_key_id.or(self.platform_public_key_id),
+            platform_public_key,
+            platform_public_key_path,
+            platform_certificate,
+            platform_certificate_path,
+            connect_timeout_ms: other.connect_timeout_ms.or(self.connect_timeout_ms),
+            read_timeout_ms: other.read_timeout_ms.or(self.read_timeout_ms),
+        }
+    }
+
+    /// 将相对路径转换为相对于dir的路径
+    fn resolve_paths(mut self, dir: &Path) -> Self {
+        for path in [
+            &mut self.private_key_path,
+            &mut self.platform_public_key_path,
+            &mut self.platform_certificate_path,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            if path.is_relative() {
+                *path = dir.join(&*path);
+            }
+        }
+        self
+    }
+}
+
+/// `WechatPay`构造器，后设置的值覆盖先设置的值
+///
+/// ```no_run
+/// use wechat_pay_rust_sdk::pay::WechatPay;
+///
+/// let wechat_pay = WechatPay::builder()
+///     .load_file("wechat_pay.toml")
+///     .load_env()
+///     .notify_url("https://mydomain.com/pay/notify")
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug, Default)]
+pub struct WechatPayBuilder {
+    config: WechatPayConfig,
+    transport: Option<Arc<dyn HttpTransport>>,
+    signer: Option<Arc<dyn Signer>>,
+    failover: Option<Failover>,
+    errors: Vec<ConfigError>,
+}
+
+impl WechatPayBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn appid(mut self, appid: impl Into<String>) -> Self {
+        self.config.appid = Some(appid.into());
+        self
+    }
+
+    pub fn mch_id(mut self, mch_id: impl Into<String>) -> Self {
+        self.config.mch_id = Some(mch_id.into());
+        self
+    }
+
+    /// 商户证书序列号
+    pub fn serial_no(mut self, serial_no: impl Into<String>) -> Self {
+        self.config.serial_no = Some(serial_no.into());
+        self
+    }
+
+    /// 商户私钥PEM，支持PKCS#8与PKCS#1
+    pub fn private_key(mut self, private_key: impl Into<SecretString>) -> Self {
+        self.config.private_key = Some(private_key.into());
+        self.config.private_key_path = None;
+        self
+    }
+
+    /// 商户私钥文件，支持PKCS#8与PKCS#1的PEM或DER格式
+    pub fn private_key_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config.private_key_path = Some(path.into());
+        self.config.private_key = None;
+        self
+    }
+
+    /// 使用自定义签名器，无需配置商户私钥
+    pub fn signer(mut self, signer: impl Signer + 'static) -> Self {
+        self.signer = Some(Arc::new(signer));
+        self
+    }
+
+    /// APIv3密钥，32字节
+    pub fn v3_key(mut self, v3_key: impl Into<SecretString>) -> Self {
+        self.config.v3_key = Some(v3_key.into());
+        self
+    }
+
+    /// 默认的回调地址
+    pub fn notify_url(mut self, notify_url: impl Into<String>) -> Self {
+        self.config.notify_url = Some(notify_url.into());
+        self
+    }
+
+    /// 接口域名，默认为`API_DOMAIN`
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.config.base_url = Some(base_url.into());
+        self
+    }
+
+    /// 使用容灾备用域名`api2.mch.weixin.qq.com`
+    pub fn backup_domain(self) -> Self {
+        self.base_url(BACKUP_API_DOMAIN)
+    }
+
+    /// 开启多域名容灾，设置后忽略base_url，见`Failover`
+    pub fn failover(mut self, failover: Failover) -> Self {
+        self.failover = Some(failover);
+        self
+    }
+
+    /// 微信支付公钥(公钥模式)，pub_key_id形如`PUB_KEY_ID_...`
+    pub fn platform_public_key(
+        mut self,
+        pub_key_id: impl Into<String>,
+        pub_key: impl Into<String>,
+    ) -> Self {
+        self.config.platform_public_key_id = Some(pub_key_id.into());
+        self.config.platform_public_key = Some(pub_key.into());
+        self.config.platform_public_key_path = None;
+        self
+    }
+
+    /// 微信支付公钥文件(公钥模式)
+    pub fn platform_public_key_path(
+        mut self,
+        pub_key_id: impl Into<String>,
+        path: impl Into<PathBuf>,
+    ) -> Self {
+        self.config.platform_public_key_id = Some(pub_key_id.into());
+        self.config.platform_public_key_path = Some(path.into());
+        self.config.platform_public_key = None;
+        self
+    }
+
+    /// 平台证书PEM，序列号从证书中读取
+    pub fn platform_certificate(mut self, certificate: impl Into<String>) -> Self {
+        self.config.platform_certificate = Some(certificate.into());
+        self.config.platform_certificate_path = None;
+        self
+    }
+
+    /// 平台证书文件
+    pub fn platform_certificate_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config.platform_certificate_path = Some(path.into());
+        self.config.platform_certificate = None;
+        self
+    }
+
+    /// 连接超时，设置超时时使用新的reqwest client
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.config.connect_timeout_ms = Some(timeout.as_millis() as u64);
+        self
+    }
+
+    /// 读超时，设置超时时使用新的reqwest client
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.config.read_timeout_ms = Some(timeout.as_millis() as u64);
+        self
+    }
+
+    /// 自定义http传输，设置后忽略超时配置
+    pub fn transport(mut self, transport: impl HttpTransport + 'static) -> Self {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// 合并配置，config中已设置的字段覆盖当前配置
+    pub fn config(mut self, config: WechatPayConfig) -> Self {
+        self.config = std::mem::take(&mut self.config).merge(config);
+        self
+    }
+
+    /// 从环境变量读取配置，未设置的变量忽略
+    ///
+    /// | 变量 | 说明 |
+    /// | --- | --- |
+    /// | WECHAT_APPID | appid |
+    /// | WECHAT_MCH_ID | 商户号 |
+    /// | WECHAT_SERIAL_NO | 商户证书序列号 |
+    /// | WECHAT_PRIVATE_KEY | 商户私钥文件路径 |
+    /// | WECHAT_PRIVATE_KEY_PEM | 商户私钥PEM |
+    /// | WECHAT_V3_KEY | APIv3密钥 |
+    /// | WECHAT_NOTIFY_URL | 默认回调地址 |
+    /// | WECHAT_BASE_URL | 接口域名 |
+    /// | WECHAT_PLATFORM_PUBLIC_KEY_ID | 微信支付公钥ID |
+    /// | WECHAT_PLATFORM_PUBLIC_KEY | 微信支付公钥文件路径 |
+    /// | WECHAT_PLATFORM_CERT | 平台证书文件路径 |
+    /// | WECHAT_CONNECT_TIMEOUT_MS | 连接超时，毫秒 |
+    /// | WECHAT_READ_TIMEOUT_MS | 读超时，毫秒 |
+    pub fn load_env(self) -> Self {
+        self.load_vars(|name| std::env::var(name).ok())
+    }
+
+    fn load_vars(mut self, var: impl Fn(&str) -> Option<String>) -> Self {
+        let var = |name: &str| var(name).filter(|value| !value.is_empty());
+        let mut timeout = |name: &'static str| {
+            var(name).and_then(|value| match value.parse::<u64>() {
+                Ok(ms) => Some(ms),
+                Err(e) => {
+                    self.errors.push(ConfigError::InvalidField {
+                        field: name,
+                        reason: e.to_string(),
+                    });
+                    None
+                }
+            })
+        };
+        let config = WechatPayConfig {
+            connect_timeout_ms: timeout("WECHAT_CONNECT_TIMEOUT_MS"),
+            read_timeout_ms: timeout("WECHAT_READ_TIMEOUT_MS"),
+            appid: var("WECHAT_APPID"),
+            mch_id: var("WECHAT_MCH_ID"),
+            serial_no: var("WECHAT_SERIAL_NO"),
+            private_key: var("WECHAT_PRIVATE_KEY_PEM").map(SecretString::from),
+            private_key_path: var("WECHAT_PRIVATE_KEY").map(PathBuf::from),
+            v3_key: var("WECHAT_V3_KEY").map(SecretString::from),
+            notify_url: var("WECHAT_NOTIFY_URL"),
+            base_url: var("WECHAT_BASE_URL"),
+            platform_public_key_id: var("WECHAT_PLATFORM_PUBLIC_KEY_ID"),
+            platform_public_key: None,
+            platform_public_key_path: var("WECHAT_PLATFORM_PUBLIC_KEY").map(PathBuf::from),
+            platform_certificate: None,
+            platform_certificate_path: var("WECHAT_PLATFORM_CERT").map(PathBuf::from),
+        };
+        self.config(config)
+    }
+
+    /// 从TOML字符串读取配置
+    pub fn load_toml(self, content: &str) -> Self {
+        let config = toml::from_str(content).map_err(|e| e.message().to_string());
+        self.parsed("<toml>", config)
+    }
+
+    /// 从JSON字符串读取配置
+    pub fn load_json(self, content: &str) -> Self {
+        let config = serde_json::from_str(content).map_err(|e| e.to_string());
+        self.parsed("<json>", config)
+    }
+
+    /// 从配置文件读取，按扩展名`.toml`或`.json`解析，文件中的相对路径以配置文件所在目录为准
+    pub fn load_file(mut self, path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(source) => {
+                self.errors.push(ConfigError::ReadFile {
+                    path: path.display().to_string(),
+                    source,
+                });
+                return self;
+            }
+        };
+        let config = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => toml::from_str(&content).map_err(|e| e.message().to_string()),
+            Some("json") => serde_json::from_str(&content).map_err(|e| e.to_string()),
+            _ => Err("unsupported config file extension, expected .toml or .json".to_string()),
+        };
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        let config = config.map(|config: WechatPayConfig| config.resolve_paths(dir));
+        self.parsed(&path.display().to_string(), config)
+    }
+
+    fn parsed(mut self, path: &str, config: Result<WechatPayConfig, String>) -> Self {
+        match config {
+            Ok(config) => self.config(config),
+            Err(reason) => {
+                self.errors.push(ConfigError::ParseFile {
+                    path: path.to_string(),
+                    reason,
+                });
+                self
+            }
+        }
+    }
+
+    /// 校验配置并创建`WechatPay`，存在多个错误时返回`ConfigError::Multiple`
+    pub fn build(self) -> Result<WechatPay, PayError> {
+        let WechatPayBuilder {
+            config,
+            transport,
+            signer,
+            failover,
+            mut errors,
+        } = self;
+        let mut required = |field: &'static str, value: Option<String>| {
+            value.filter(|value| !value.is_empty()).unwrap_or_else(|| {
+                errors.push(ConfigError::MissingField(field));
+                String::new()
+            })
+        };
+        let appid = required("appid", config.appid);
+        let mch_id = required("mch_id", config.mch_id);
+        let serial_no = required("serial_no", config.serial_no);
+        let v3_key = required(
+            "v3_key",
+            config.v3_key.map(|key| key.expose_secret().to_string()),
+        );
+        if !v3_key.is_empty()
+            && let Err(e) = ApiV3Key::new(&v3_key)
+        {
+            errors.push(e);
+        }
+
+        // 自定义签名器优先，否则解析商户私钥
+        let signer = match signer {
+            Some(signer) => Some(signer),
+            None => {
+                let private_key = match (config.private_key, config.private_key_path) {
+                    (Some(pem), _) => Some(pem.expose_secret().as_bytes().to_vec()),
+                    (None, Some(path)) => read_file(&path, &mut errors),
+                    (None, None) => {
+                        errors.push(ConfigError::MissingField("private_key"));
+                        None
+                    }
+                };
+                match private_key.map(RsaSigner::new) {
+                    Some(Ok(signer)) => Some(Arc::new(signer) as Arc<dyn Signer>),
+                    Some(Err(PayError::ConfigError(e))) => {
+                        errors.push(e);
+                        None
+                    }
+                    Some(Err(e)) => {
+                        errors.push(ConfigError::InvalidPrivateKey(e.to_string()));
+                        None
+                    }
+                    None => None,
+                }
+            }
+        };
+
+        let base_url = config.base_url.unwrap_or_else(|| API_DOMAIN.to_string());
+        if !base_url.starts_with("https://") && !base_url.starts_with("http://") {
+            errors.push(ConfigError::InvalidField {
+                field: "base_url",
+                reason: format!("{} is not a http(s) url", base_url),
+            });
+        }
+
+        let platform_public_key =
+            match (config.platform_public_key, config.platform_public_key_path) {
+                (Some(pem), _) => Some(pem),
+                (None, Some(path)) => read_file(&path, &mut errors)
+                    .map(|pem| String::from_utf8_lossy(&pem).to_string()),
+                (None, None) => None,
+            };
+        let platform_public_key = match (config.platform_public_key_id, platform_public_key) {
+            (Some(id), Some(pem)) => match RsaPublicKey::from_public_key_pem(pem.as_str()) {
+                Ok(_) => Some((id, pem)),
+                Err(e) => {
+                    errors.push(ConfigError::InvalidField {
+                        field: "platform_public_key",
+                        reason: e.to_string(),
+                    });
+                    None
+                }
+            },
+            (Some(_), None) => {
+                errors.push(ConfigError::MissingField("platform_public_key"));
+                None
+            }
+            (None, Some(_)) => {
+                errors.push(ConfigError::MissingField("platform_public_key_id"));
+                None
+            }
+            (None, None) => None,
+        };
+
+        let platform_certificate = match (
+            config.platform_certificate,
+            config.platform_certificate_path,
+        ) {
+            (Some(pem), _) => Some(pem.into_bytes()),
+            (None, Some(path)) => read_file(&path, &mut errors),
+            (None, None) => None,
+        };
+        let platform_certificate = platform_certificate.and_then(|pem| {
+            match (util::x509_serial_no(&pem), util::x509_to_pem(&pem)) {
+                (Ok(serial_no), Ok(pub_key)) => Some((serial_no, pub_key)),
+                (Err(e), _) | (_, Err(e)) => {
+                    errors.push(ConfigError::InvalidField {
+                        field: "platform_certificate",
+                        reason: e.to_string(),
+                    });
+                    None
+                }
+            }
+        });
+
+        let transport = match transport {
+            Some(transport) => Some(transport),
+            None => timeout_transport(
+                config.connect_timeout_ms,
+                config.read_timeout_ms,
+                &mut errors,
+            ),
+        };
+
+        match errors.len() {
+            0 => {}
+            1 => return Err(errors.remove(0).into()),
+            _ => return Err(ConfigError::Multiple(errors).into()),
+        }
+
+        let mut wechat_pay = WechatPay::from_parts(
+            appid.as_str(),
+            mch_id.as_str(),
+            serial_no.as_str(),
+            v3_key.as_str(),
+            config.notify_url.as_deref().unwrap_or_default(),
+            signer,
+        )
+        .with_base_url(base_url);
+        if let Some(transport) = transport {
+            wechat_pay.transport = Some(transport);
+        }
+        if let Some(failover) = failover {
+            wechat_pay = wechat_pay.with_failover(failover);
+        }
+        if let Some((pub_key_id, pub_key)) = platform_public_key {
+            wechat_pay = wechat_pay.with_platform_public_key(pub_key_id, pub_key);
+        }
+        if let Some((serial_no, pub_key)) = platform_certificate {
+            wechat_pay = wechat_pay.with_platform_pub_key(serial_no, pub_key);
+        }
+        Ok(wechat_pay)
+    }
+}
+
+fn read_file(path: &Path, errors: &mut Vec<ConfigError>) -> Option<Vec<u8>> {
+    std::fs::read(path)
+        .map_err(|source| {
+            errors.push(ConfigError::ReadFile {
+                path: path.display().to_string(),
+                source,
+            })
+        })
+        .ok()
+}
+
+#[cfg(any(feature = "async", feature = "blocking"))]
+fn timeout_transport(
+    connect_timeout_ms: Option<u64>,
+    read_timeout_ms: Option<u64>,
+    errors: &mut Vec<ConfigError>,
+) -> Option<Arc<dyn HttpTransport>> {
+    use crate::transport::ReqwestTransport;
+    if connect_timeout_ms.is_none() && read_timeout_ms.is_none() {
+        return None;
+    }
+    let mut builder = ReqwestTransport::builder();
+    if let Some(ms) = connect_timeout_ms {
+        builder = builder.connect_timeout(Duration::from_millis(ms));
+    }
+    if let Some(ms) = read_timeout_ms {
+        builder = builder.read_timeout(Duration::from_millis(ms));
+    }
+    match builder.build() {
+        Ok(transport) => Some(Arc::new(transport)),
+        Err(e) => {
+            errors.push(ConfigError::InvalidField {
+                field: "timeout",
+                reason: e.to_string(),
+            });
+            None
+        }
+    }
+}
+
+#[cfg(not(any(feature = "async", feature = "blocking")))]
+fn timeout_transport(
+    connect_timeout_ms: Option<u64>,
+    read_timeout_ms: Option<u64>,
+    errors: &mut Vec<ConfigError>,
+) -> Option<Arc<dyn HttpTransport>> {
+    if connect_timeout_ms.is_some() || read_timeout_ms.is_some() {
+        errors.push(ConfigError::InvalidField {
+            field: "timeout",
+            reason: "timeouts require the reqwest transport, set a custom transport instead"
+                .to_string(),
+        });
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builder::WechatPayBuilder;
+    use crate::error::{ConfigError, PayError};
+    use crate::pay::{WechatPay, BACKUP_API_DOMAIN};
+    use rsa::pkcs8::{DecodePrivateKey, EncodePublicKey, LineEnding};
+    use rsa::{RsaPrivateKey, RsaPublicKey};
+    use std::collections::HashMap;
+
+    const PRIVATE_KEY: &str = include_str!("../tests/fixtures/platform_key.pem");
+    const PLATFORM_CERT: &str = include_str!("../tests/fixtures/platform_cert.pem");
+
+    #[test]
+    fn test_builder_from_file() {
+        let wechat_pay = WechatPay::builder()
+            .load_file(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/fixtures/wechat_pay.toml"
+            ))
+            .build()
+            .unwrap();
+        assert_eq!(wechat_pay.appid, "wxd678efh567hg6787");
+        assert_eq!(wechat_pay.mch_id, "1230000109");
+        assert_eq!(wechat_pay.base_url, BACKUP_API_DOMAIN);
+        assert_eq!(wechat_pay.notify_url, "https://mydomain.com/pay/notify");
+        assert!(wechat_pay.signer().is_ok());
+        assert_eq!(wechat_pay.platform_pub_keys.len(), 1);
+        let (serial_no, _) = wechat_pay.platform_encrypt_key().unwrap();
+        assert!(!serial_no.is_empty());
+        assert_eq!(serial_no, serial_no.to_uppercase());
+    }
+
+    #[test]
+    fn test_builder_from_json_and_env() {
+        let json = serde_json::json!({
+            "appid": "wxd678efh567hg6787",
+            "mch_id": "1230000109",
+            "serial_no": "MERCHANT_SERIAL",
+            "private_key": PRIVATE_KEY,
+            "platform_certificate": PLATFORM_CERT,
+            "connect_timeout_ms": 3000,
+        });
+        let vars = HashMap::from([
+            ("WECHAT_V3_KEY", "0123456789abcdef0123456789abcdef"),
+            ("WECHAT_MCH_ID", "1900000100"),
+            ("WECHAT_NOTIFY_URL", ""),
+        ]);
+        let platform_public_key =
+            RsaPublicKey::from(RsaPrivateKey::from_pkcs8_pem(PRIVATE_KEY).unwrap())
+                .to_public_key_pem(LineEnding::LF)
+                .unwrap();
+        let wechat_pay = WechatPayBuilder::new()
+            .load_json(&json.to_string())
+            .load_vars(|name| vars.get(name).map(|value| value.to_string()))
+            .platform_public_key("PUB_KEY_ID_0114", platform_public_key)
+            .build()
+            .unwrap();
+        // 环境变量覆盖配置文件，空变量忽略
+        assert_eq!(wechat_pay.mch_id, "1900000100");
+        assert_eq!(wechat_pay.notify_url, "");
+        assert_eq!(
+            wechat_pay.platform_public_key_id.as_deref(),
+            Some("PUB_KEY_ID_0114")
+        );
+        assert_eq!(format!("{:?}", wechat_pay).matches(PRIVATE_KEY).count(), 0);
+
+        // 微信支付公钥在build时解析
+        let error = WechatPayBuilder::new()
+            .load_json(&json.to_string())
+            .load_vars(|name| vars.get(name).map(|value| value.to_string()))
+            .platform_public_key("PUB_KEY_ID_0114", "-----BEGIN PUBLIC KEY-----")
+            .build()
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            PayError::ConfigError(ConfigError::InvalidField {
+                field: "platform_public_key",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_builder_errors() {
+        let vars = HashMap::from([
+            ("WECHAT_APPID", "wxd678efh567hg6787"),
+            ("WECHAT_V3_KEY", "too short"),
+            ("WECHAT_PRIVATE_KEY_PEM", "not a key"),
+            ("WECHAT_BASE_URL", "api2.mch.weixin.qq.com"),
+            ("WECHAT_READ_TIMEOUT_MS", "10s"),
+            ("WECHAT_PLATFORM_PUBLIC_KEY_ID", "PUB_KEY_ID_0114"),
+        ]);
+        let error = WechatPayBuilder::new()
+            .load_vars(|name| vars.get(name).map(|value| value.to_string()))
+            .build()
+            .unwrap_err();
+        let PayError::ConfigError(ConfigError::Multiple(errors)) = &error else {
+            panic!("expected multiple errors, got {:?}", error);
+        };
+        assert_eq!(errors.len(), 7, "{}", error);
+        assert!(matches!(
+            errors[0],
+            ConfigError::InvalidField {
+                field: "WECHAT_READ_TIMEOUT_MS",
+                ..
+            }
+        ));
+        assert!(matches!(errors[1], ConfigError::MissingField("mch_id")));
+        assert!(matches!(errors[2], ConfigError::MissingField("serial_no")));
+        assert!(matches!(errors[3], ConfigError::InvalidV3Key(9)));
+        assert!(matches!(errors[4], ConfigError::InvalidPrivateKey(_)));
+        assert!(error.to_string().contains("mch_id is required"));
+
+        let error = WechatPayBuilder::new()
+            .load_toml("appid = 1")
+            .load_file("wechat_pay.yaml")
+            .build()
+            .unwrap_err();
+        assert!(error.to_string().contains("<toml>"), "{}", error);
+        assert!(error.to_string().contains("wechat_pay.yaml"), "{}", error);
+    }
+}
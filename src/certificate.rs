@@ -0,0 +1,567 @@
+use crate::error::{PayError, WechatApiError};
+use crate::pay::{PayNotifyTrait, WechatPay};
+use crate::request::HttpMethod;
+use crate::response::CertificateResponse;
+use crate::util;
+use crate::verify::{self, ResponseVerifier};
+use crate::{debug, error};
+use http::header::HeaderMap;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+
+#[cfg(feature = "async")]
+use maybe_async::maybe_async as maybe_async_attr;
+#[cfg(not(feature = "async"))]
+use maybe_async::must_be_sync as maybe_async_attr;
+
+/// 平台证书，pub_key为证书中的公钥(PEM)
+#[derive(Debug, Clone)]
+pub struct PlatformCertificate {
+    pub serial_no: String,
+    pub pub_key: String,
+    /// 证书生效时间(秒)，证书轮换时新证书会在生效前下发
+    pub effective_timestamp: i64,
+    /// 证书过期时间(秒)
+    pub expire_timestamp: i64,
+}
+
+#[derive(Debug, Default)]
+struct CertificateState {
+    certificates: HashMap<String, PlatformCertificate>,
+    refreshed_at: i64,
+    /// 最近一次开始下载的时间，无论成功与否
+    attempted_at: i64,
+}
+
+#[cfg(not(feature = "async"))]
+type RefreshLock = std::sync::Mutex<()>;
+#[cfg(feature = "async")]
+type RefreshLock = tokio::sync::Mutex<()>;
+
+/// 平台证书管理器，下载并缓存平台证书，按序列号提供验签公钥
+///
+/// 在证书即将过期或超过刷新间隔时调用`ensure_fresh`自动重新下载，并发调用只会有一个下载请求；
+/// 下载失败时只要缓存中仍有未过期的证书就继续使用缓存
+#[derive(Debug)]
+pub struct PlatformCertificateManager {
+    state: RwLock<CertificateState>,
+    refresh_lock: RefreshLock,
+    /// 已完成的下载次数，用于合并并发的刷新
+    attempts: AtomicU64,
+    /// 证书过期前多少秒开始刷新
+    refresh_ahead: i64,
+    /// 两次下载的最长间隔(秒)
+    refresh_interval: i64,
+    /// 两次下载的最短间隔(秒)，限制下载失败或遇到未知序列号时的重试频率
+    refresh_cooldown: i64,
+}
+
+impl Default for PlatformCertificateManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PlatformCertificateManager {
+    pub fn new() -> Self {
+        Self {
+            state: RwLock::new(CertificateState::default()),
+            refresh_lock: RefreshLock::default(),
+            attempts: AtomicU64::new(0),
+            refresh_ahead: 24 * 60 * 60,
+            refresh_interval: 12 * 60 * 60,
+            refresh_cooldown: 60,
+        }
+    }
+
+    /// 设置证书过期前多少秒开始刷新，默认1天
+    pub fn with_refresh_ahead(mut self, seconds: i64) -> Self {
+        self.refresh_ahead = seconds;
+        self
+    }
+
+    /// 设置两次下载的最长间隔(秒)，默认12小时
+    pub fn with_refresh_interval(mut self, seconds: i64) -> Self {
+        self.refresh_interval = seconds;
+        self
+    }
+
+    /// 设置两次下载的最短间隔(秒)，默认60秒
+    pub fn with_refresh_cooldown(mut self, seconds: i64) -> Self {
+        self.refresh_cooldown = seconds;
+        self
+    }
+
+    /// 按序列号获取平台公钥
+    pub fn public_key_for(&self, serial_no: &str) -> Option<String> {
+        self.state
+            .read()
+            .unwrap()
+            .certificates
+            .get(serial_no)
+            .map(|certificate| certificate.pub_key.clone())
+    }
+
+    /// 当前缓存的平台证书
+    pub fn certificates(&self) -> Vec<PlatformCertificate> {
+        self.state
+            .read()
+            .unwrap()
+            .certificates
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// 缓存为空、有证书即将过期或超过刷新间隔时需要刷新
+    pub fn needs_refresh(&self) -> bool {
+        let now = chrono::Local::now().timestamp();
+        let state = self.state.read().unwrap();
+        state.certificates.is_empty()
+            || now - state.refreshed_at >= self.refresh_interval
+            || state
+                .certificates
+                .values()
+                .any(|certificate| certificate.expire_timestamp - now <= self.refresh_ahead)
+    }
+
+    /// 缓存中是否有未过期的证书
+    fn has_valid_certificate(&self) -> bool {
+        let now = chrono::Local::now().timestamp();
+        self.state
+            .read()
+            .unwrap()
+            .certificates
+            .values()
+            .any(|certificate| certificate.expire_timestamp > now)
+    }
+
+    fn in_cooldown(&self) -> bool {
+        let now = chrono::Local::now().timestamp();
+        now - self.state.read().unwrap().attempted_at < self.refresh_cooldown
+    }
+
+    /// 下次`ensure_fresh`时重新下载，用于在同步代码中遇到未知序列号的场景
+    pub fn invalidate(&self) {
+        self.state.write().unwrap().refreshed_at = 0;
+    }
+
+    /// 需要刷新时下载平台证书，下载失败但缓存中仍有未过期的证书时继续使用缓存
+    #[maybe_async_attr]
+    pub async fn ensure_fresh(&self, wechat_pay: &WechatPay) -> Result<(), PayError> {
+        if !self.needs_refresh() {
+            return Ok(());
+        }
+        let usable = self.has_valid_certificate();
+        if usable && self.in_cooldown() {
+            return Ok(());
+        }
+        match self.refresh(wechat_pay).await {
+            Err(_e) if usable => {
+                error!("refresh platform certificates error, use cached: {}", _e);
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    /// 按序列号获取平台公钥，序列号未知时(例如微信支付已切换新证书)重新下载平台证书
+    #[maybe_async_attr]
+    pub async fn public_key_or_refresh(
+        &self,
+        wechat_pay: &WechatPay,
+        serial_no: &str,
+    ) -> Result<Option<String>, PayError> {
+        if let Some(pub_key) = self.public_key_for(serial_no) {
+            return Ok(Some(pub_key));
+        }
+        if self.in_cooldown() {
+            return Ok(None);
+        }
+        self.refresh(wechat_pay).await?;
+        Ok(self.public_key_for(serial_no))
+    }
+
+    /// 下载平台证书，并发调用时只有一个调用会下载，其余调用等待其完成后直接返回；
+    /// 冷却期内已下载且缓存仍是最新时不再下载
+    #[maybe_async_attr]
+    pub async fn refresh(&self, wechat_pay: &WechatPay) -> Result<(), PayError> {
+        let attempts = self.attempts.load(Ordering::SeqCst);
+        let _guard = lock(&self.refresh_lock).await;
+        // 等待期间其他调用已完成下载，刚下载过且缓存仍是最新时也不再重复下载
+        if self.in_cooldown() && !self.needs_refresh() {
+            return Ok(());
+        }
+        if self.attempts.load(Ordering::SeqCst) != attempts {
+            return if self.has_valid_certificate() {
+                Ok(())
+            } else {
+                Err(PayError::VerifyError(
+                    "platform certificate refresh failed".to_string(),
+                ))
+            };
+        }
+        self.state.write().unwrap().attempted_at = chrono::Local::now().timestamp();
+        let result = self.download(wechat_pay).await;
+        self.attempts.fetch_add(1, Ordering::SeqCst);
+        result
+    }
+
+    /// 下载平台证书，用新证书校验下载应答的签名后替换缓存
+    #[maybe_async_attr]
+    async fn download(&self, wechat_pay: &WechatPay) -> Result<(), PayError> {
+        let (status, headers, text) = wechat_pay
+            .send_unverified(
+                HttpMethod::GET,
+                "/v3/certificates",
+                String::new(),
+                HeaderMap::new(),
+                true,
+            )
+            .await?;
+        if !status.is_success() {
+            return Err(WechatApiError::from_body(status, text.as_bytes()).into());
+        }
+        let response: CertificateResponse = serde_json::from_str(&text)?;
+        let mut certificates = HashMap::new();
+        for certificate in response.data.unwrap_or_default() {
+            let encrypt_certificate = &certificate.encrypt_certificate;
+            let pem = wechat_pay.decrypt_bytes(
+                encrypt_certificate.ciphertext.as_str(),
+                encrypt_certificate.nonce.as_str(),
+                encrypt_certificate.associated_data.as_str(),
+            )?;
+            let serial_no = util::x509_serial_no(pem.as_slice())
+                .map_err(|e| PayError::VerifyError(e.to_string()))?;
+            if !same_serial_no(serial_no.as_str(), certificate.serial_no.as_str()) {
+                return Err(PayError::VerifyError(format!(
+                    "platform certificate serial_no {} does not match {}",
+                    certificate.serial_no, serial_no
+                )));
+            }
+            let (effective_timestamp, expire_timestamp) = util::x509_validity(pem.as_slice())
+                .map_err(|e| PayError::VerifyError(e.to_string()))?;
+            // 只跳过已过期的证书，尚未生效的新证书先缓存，用于验证切换后的签名
+            if expire_timestamp <= chrono::Local::now().timestamp() {
+                debug!("platform certificate is expired: {:?}", certificate);
+                continue;
+            }
+            let pub_key = util::x509_to_pem(pem.as_slice())
+                .map_err(|e| PayError::VerifyError(e.to_string()))?;
+            certificates.insert(
+                certificate.serial_no.clone(),
+                PlatformCertificate {
+                    serial_no: certificate.serial_no,
+                    pub_key,
+                    effective_timestamp,
+                    expire_timestamp,
+                },
+            );
+        }
+        if certificates.is_empty() {
+            return Err(PayError::VerifyError(
+                "no valid platform certificate".to_string(),
+            ));
+        }
+        let verifier: HashMap<String, String> = certificates
+            .iter()
+            .map(|(serial_no, certificate)| (serial_no.clone(), certificate.pub_key.clone()))
+            .collect();
+        verify::verify_response_headers(&verifier, &headers, text.as_str())?;
+        let mut state = self.state.write().unwrap();
+        state.certificates = certificates;
+        state.refreshed_at = chrono::Local::now().timestamp();
+        Ok(())
+    }
+}
+
+/// 证书序列号为十六进制，解析证书得到的序列号不含前导0
+fn same_serial_no(a: &str, b: &str) -> bool {
+    a.trim_start_matches('0')
+        .eq_ignore_ascii_case(b.trim_start_matches('0'))
+}
+
+#[cfg(not(feature = "async"))]
+#[maybe_async_attr]
+async fn lock(lock: &RefreshLock) -> std::sync::MutexGuard<'_, ()> {
+    lock.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(feature = "async")]
+#[maybe_async_attr]
+async fn lock(lock: &RefreshLock) -> tokio::sync::MutexGuard<'_, ()> {
+    lock.lock().await
+}
+
+impl ResponseVerifier for PlatformCertificateManager {
+    fn verify(&self, serial_no: &str, message: &str, signature: &str) -> Result<(), PayError> {
+        let state = self.state.read().unwrap();
+        let certificate = state.certificates.get(serial_no).ok_or_else(|| {
+            PayError::SignatureError(format!("platform certificate {} not found", serial_no))
+        })?;
+        crate::sign::sha256_verify(certificate.pub_key.as_str(), message, signature)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::async_impl::pay::tests::{stub_serial_response, stub_server, stub_wechat_pay};
+    use crate::certificate::PlatformCertificateManager;
+    use crate::error::{PayError, WechatErrorCode};
+    use crate::model::NativeParams;
+    use crate::pay::tests::encrypt_resource;
+    use crate::pay::WechatPay;
+    use rsa::pkcs8::DecodePrivateKey;
+    use rsa::rand_core::OsRng;
+    use rsa::RsaPrivateKey;
+    use std::sync::Arc;
+
+    const PLATFORM_CERT: &str = include_str!("../tests/fixtures/platform_cert.pem");
+    pub(crate) const PLATFORM_KEY: &str = include_str!("../tests/fixtures/platform_key.pem");
+    /// PLATFORM_CERT的序列号
+    pub(crate) const PLATFORM_SERIAL: &str = "5157F09EFDC096DE15EBE81A47057A7232F7F8C1";
+    /// 轮换中的新证书，2100年生效
+    const NEXT_PLATFORM_CERT: &str = include_str!("../tests/fixtures/platform_cert_next.pem");
+    const NEXT_PLATFORM_SERIAL: &str = "6A2E1F3C4B5D6E7F8091A2B3C4D5E6F708192A3B";
+    const CODE_URL: &str = r#"{"code_url":"weixin://wxpay/bizpayurl?pr=p4lpSuKzz"}"#;
+
+    /// 证书下载应答，serial_no为应答中声明的证书序列号
+    pub(crate) fn certificates_response(platform_key: &RsaPrivateKey, serial_no: &str) -> String {
+        certificates_list_response(platform_key, &[(serial_no, PLATFORM_CERT)])
+    }
+
+    /// 包含多张证书的下载应答，certificates为(声明的序列号, 证书PEM)
+    fn certificates_list_response(
+        platform_key: &RsaPrivateKey,
+        certificates: &[(&str, &str)],
+    ) -> String {
+        let nonce = "0123456789ab";
+        let data: Vec<_> = certificates
+            .iter()
+            .map(|(serial_no, certificate)| {
+                let ciphertext = encrypt_resource(
+                    "0123456789abcdef0123456789abcdef",
+                    nonce,
+                    "certificate",
+                    certificate,
+                );
+                serde_json::json!({
+                    "serial_no": serial_no,
+                    "effective_time": "2026-01-01T00:00:00+08:00",
+                    "expire_time": "2126-01-01T00:00:00+08:00",
+                    "encrypt_certificate": {
+                        "algorithm": "AEAD_AES_256_GCM",
+                        "nonce": nonce,
+                        "associated_data": "certificate",
+                        "ciphertext": ciphertext
+                    }
+                })
+            })
+            .collect();
+        let body = serde_json::json!({ "data": data }).to_string();
+        stub_serial_response(platform_key, PLATFORM_SERIAL, 200, body.as_str())
+    }
+
+    /// 桩服务依次返回responses，应答均由fixture平台私钥签名
+    fn certificate_fixture<F>(
+        manager: PlatformCertificateManager,
+        responses: F,
+    ) -> (WechatPay, Arc<PlatformCertificateManager>)
+    where
+        F: FnOnce(&RsaPrivateKey) -> Vec<String>,
+    {
+        let platform_key = RsaPrivateKey::from_pkcs8_pem(PLATFORM_KEY).unwrap();
+        let base_url = stub_server(responses(&platform_key));
+        let manager = Arc::new(manager);
+        // 手动配置的平台公钥与证书不匹配，验签必须经过证书管理器
+        let other_key = RsaPrivateKey::new(&mut OsRng, 1024).unwrap();
+        let wechat_pay = stub_wechat_pay(base_url.as_str(), &other_key)
+            .with_certificate_manager(manager.clone());
+        (wechat_pay, manager)
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_certificate_manager() {
+        let (wechat_pay, manager) =
+            certificate_fixture(PlatformCertificateManager::new(), |platform_key| {
+                vec![
+                    certificates_response(platform_key, PLATFORM_SERIAL),
+                    stub_serial_response(platform_key, PLATFORM_SERIAL, 200, CODE_URL),
+                ]
+            });
+        assert!(manager.needs_refresh());
+        // 首次请求前自动下载平台证书
+        let response = wechat_pay
+            .native_pay(NativeParams::new("测试支付1分", "1243243", 1.into()))
+            .await
+            .unwrap();
+        assert_eq!(
+            response.code_url.as_deref(),
+            Some("weixin://wxpay/bizpayurl?pr=p4lpSuKzz")
+        );
+        assert!(!manager.needs_refresh());
+        assert!(manager.public_key_for(PLATFORM_SERIAL).is_some());
+        assert!(manager.public_key_for("OTHER_SERIAL").is_none());
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_certificate_manager_refresh() {
+        let (wechat_pay, manager) =
+            certificate_fixture(PlatformCertificateManager::new(), |platform_key| {
+                vec![
+                    certificates_response(platform_key, PLATFORM_SERIAL),
+                    stub_serial_response(platform_key, PLATFORM_SERIAL, 200, CODE_URL),
+                ]
+            });
+        manager.ensure_fresh(&wechat_pay).await.unwrap();
+        assert!(!manager.needs_refresh());
+        assert!(manager.public_key_for(PLATFORM_SERIAL).is_some());
+        let response = wechat_pay
+            .native_pay(NativeParams::new("测试支付1分", "1243243", 1.into()))
+            .await
+            .unwrap();
+        assert_eq!(
+            response.code_url.as_deref(),
+            Some("weixin://wxpay/bizpayurl?pr=p4lpSuKzz")
+        );
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_certificate_serial_mismatch() {
+        let (wechat_pay, manager) =
+            certificate_fixture(PlatformCertificateManager::new(), |platform_key| {
+                vec![certificates_response(platform_key, "OTHER_SERIAL")]
+            });
+        let error = manager.refresh(&wechat_pay).await.unwrap_err();
+        assert!(matches!(error, PayError::VerifyError(_)));
+        assert!(manager.certificates().is_empty());
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_certificate_refresh_fallback() {
+        let manager = PlatformCertificateManager::new()
+            .with_refresh_interval(0)
+            .with_refresh_cooldown(0);
+        let (wechat_pay, manager) = certificate_fixture(manager, |platform_key| {
+            vec![
+                certificates_response(platform_key, PLATFORM_SERIAL),
+                stub_serial_response(platform_key, PLATFORM_SERIAL, 500, "{}"),
+            ]
+        });
+        manager.ensure_fresh(&wechat_pay).await.unwrap();
+        assert!(manager.needs_refresh());
+        // 下载失败或连接失败时继续使用缓存中未过期的证书
+        manager.ensure_fresh(&wechat_pay).await.unwrap();
+        manager.ensure_fresh(&wechat_pay).await.unwrap();
+        assert!(manager.public_key_for(PLATFORM_SERIAL).is_some());
+
+        // 缓存为空时返回下载失败，错误应答解析为WechatApiError
+        let (wechat_pay, manager) =
+            certificate_fixture(PlatformCertificateManager::new(), |platform_key| {
+                vec![stub_serial_response(
+                    platform_key,
+                    PLATFORM_SERIAL,
+                    401,
+                    r#"{"code":"SIGN_ERROR","message":"签名错误"}"#,
+                )]
+            });
+        let error = manager.ensure_fresh(&wechat_pay).await.unwrap_err();
+        let api_error = error.api_error().unwrap();
+        assert_eq!(api_error.status, http::StatusCode::UNAUTHORIZED);
+        assert_eq!(api_error.code, WechatErrorCode::SignError);
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_unknown_serial_refresh() {
+        let (wechat_pay, manager) =
+            certificate_fixture(PlatformCertificateManager::new(), |platform_key| {
+                vec![certificates_response(platform_key, PLATFORM_SERIAL)]
+            });
+        // 未知序列号触发下载
+        let pub_key = wechat_pay
+            .fetch_platform_pub_key(PLATFORM_SERIAL)
+            .await
+            .unwrap();
+        assert!(pub_key.is_some());
+        assert_eq!(pub_key, manager.public_key_for(PLATFORM_SERIAL));
+        // 刚下载过，冷却期内不会为未知序列号重复下载
+        let pub_key = wechat_pay
+            .fetch_platform_pub_key("OTHER_SERIAL")
+            .await
+            .unwrap();
+        assert!(pub_key.is_none());
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_certificate_not_yet_effective() {
+        let (wechat_pay, manager) =
+            certificate_fixture(PlatformCertificateManager::new(), |platform_key| {
+                vec![certificates_list_response(
+                    platform_key,
+                    &[
+                        (PLATFORM_SERIAL, PLATFORM_CERT),
+                        (NEXT_PLATFORM_SERIAL, NEXT_PLATFORM_CERT),
+                    ],
+                )]
+            });
+        manager.refresh(&wechat_pay).await.unwrap();
+        // 轮换中尚未生效的新证书同样缓存，可以验证切换后的签名
+        assert!(manager.public_key_for(NEXT_PLATFORM_SERIAL).is_some());
+        // 加密只使用已生效的证书
+        let (serial_no, _) = wechat_pay.platform_encrypt_key().unwrap();
+        assert_eq!(serial_no, PLATFORM_SERIAL);
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_refresh_after_download() {
+        let (wechat_pay, manager) =
+            certificate_fixture(PlatformCertificateManager::new(), |platform_key| {
+                vec![certificates_response(platform_key, PLATFORM_SERIAL)]
+            });
+        manager.refresh(&wechat_pay).await.unwrap();
+        // 桩服务只返回一次证书，刚下载完成后再次刷新直接使用缓存
+        manager.refresh(&wechat_pay).await.unwrap();
+        assert!(manager.public_key_for(PLATFORM_SERIAL).is_some());
+    }
+
+    #[test]
+    #[cfg(not(feature = "async"))]
+    fn test_single_flight_refresh() {
+        let (wechat_pay, manager) =
+            certificate_fixture(PlatformCertificateManager::new(), |platform_key| {
+                vec![certificates_response(platform_key, PLATFORM_SERIAL)]
+            });
+        // 桩服务只返回一次证书，并发刷新只会下载一次
+        std::thread::scope(|scope| {
+            let handles = (0..4)
+                .map(|_| scope.spawn(|| manager.ensure_fresh(&wechat_pay)))
+                .collect::<Vec<_>>();
+            for handle in handles {
+                handle.join().unwrap().unwrap();
+            }
+        });
+        assert!(manager.public_key_for(PLATFORM_SERIAL).is_some());
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "async")]
+    async fn test_single_flight_refresh() {
+        let (wechat_pay, manager) =
+            certificate_fixture(PlatformCertificateManager::new(), |platform_key| {
+                vec![certificates_response(platform_key, PLATFORM_SERIAL)]
+            });
+        // 桩服务只返回一次证书，并发刷新只会下载一次
+        let results = tokio::join!(
+            manager.ensure_fresh(&wechat_pay),
+            manager.ensure_fresh(&wechat_pay),
+            manager.ensure_fresh(&wechat_pay),
+            manager.ensure_fresh(&wechat_pay),
+        );
+        results.0.unwrap();
+        results.1.unwrap();
+        results.2.unwrap();
+        results.3.unwrap();
+        assert!(manager.public_key_for(PLATFORM_SERIAL).is_some());
+    }
+}
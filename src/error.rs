@@ -1,17 +1,283 @@
+use crate::response::{ErrorDetail, ErrorResponse};
+use http::StatusCode;
+use std::fmt::{Display, Formatter};
+
 #[derive(Debug, thiserror::Error)]
 pub enum PayError {
+    #[cfg(any(feature = "async", feature = "blocking"))]
     #[error("http error: {0}")]
     RequestError(#[from] reqwest::Error),
+    #[error("transport error: {0}")]
+    TransportError(String),
+    #[error("sign error: {0}")]
+    SignError(String),
+    #[error("config error: {0}")]
+    ConfigError(#[from] ConfigError),
+    #[error("header error: {0}")]
+    HeaderError(#[from] http::header::InvalidHeaderValue),
     #[error("pay error: {0}")]
-    WechatError(String),
+    WechatError(Box<WechatApiError>),
     #[error("json error: {0}")]
     JsonError(#[from] serde_json::Error),
     #[error("Decrypt error: {0}")]
     DecryptError(String),
+    #[error("Encrypt error: {0}")]
+    EncryptError(String),
     #[error("Base64 decode error: {0}")]
     DecodeError(#[from] base64::DecodeError),
     #[error("verify error: {0}")]
     VerifyError(String),
+    #[error("response signature error: {0}")]
+    SignatureError(String),
+    #[error("bill error: {0}")]
+    BillError(String),
     #[error("weixin not found error")]
     WeixinNotFound,
 }
+
+impl PayError {
+    /// 是否为可以重试的临时错误：连接失败、超时，或微信支付返回的可重试错误
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            #[cfg(any(feature = "async", feature = "blocking"))]
+            PayError::RequestError(e) => e.is_timeout() || e.is_connect(),
+            PayError::TransportError(_) => true,
+            PayError::WechatError(e) => e.is_retryable(),
+            _ => false,
+        }
+    }
+
+    /// 连接失败，请求未到达微信支付，可以切换到备用域名重发
+    ///
+    /// 自定义`HttpTransport`连接失败时应返回`TransportError`
+    pub fn is_connect_error(&self) -> bool {
+        match self {
+            #[cfg(any(feature = "async", feature = "blocking"))]
+            PayError::RequestError(e) => e.is_connect(),
+            PayError::TransportError(_) => true,
+            _ => false,
+        }
+    }
+
+    /// 微信支付返回的错误
+    pub fn api_error(&self) -> Option<&WechatApiError> {
+        match self {
+            PayError::WechatError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<WechatApiError> for PayError {
+    fn from(error: WechatApiError) -> Self {
+        PayError::WechatError(Box::new(error))
+    }
+}
+
+/// 商户配置错误
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("{0} is required")]
+    MissingField(&'static str),
+    #[error("invalid {field}: {reason}")]
+    InvalidField { field: &'static str, reason: String },
+    #[error("read {path} error: {source}")]
+    ReadFile {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("invalid private key: {0}")]
+    InvalidPrivateKey(String),
+    #[error("APIv3 key must be 32 bytes, got {0}")]
+    InvalidV3Key(usize),
+    #[error("parse config file {path} error: {reason}")]
+    ParseFile { path: String, reason: String },
+    #[error("{}", .0.iter().map(|e| e.to_string()).collect::<Vec<_>>().join("; "))]
+    Multiple(Vec<ConfigError>),
+}
+
+/// 微信支付非2xx应答的错误信息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WechatApiError {
+    /// http状态码
+    pub status: StatusCode,
+    /// 【错误码】
+    pub code: WechatErrorCode,
+    /// 【错误信息】
+    pub message: String,
+    /// 【错误详情】
+    pub detail: Option<ErrorDetail>,
+}
+
+impl WechatApiError {
+    pub fn new(status: StatusCode, error: ErrorResponse) -> Self {
+        Self {
+            status,
+            code: error.code.unwrap_or_default().into(),
+            message: error.message.unwrap_or_default(),
+            detail: error.detail,
+        }
+    }
+
+    /// 解析应答报文，无法解析时以报文作为错误信息
+    pub fn from_body(status: StatusCode, body: &[u8]) -> Self {
+        match serde_json::from_slice::<ErrorResponse>(body) {
+            Ok(error) => Self::new(status, error),
+            Err(_) => Self {
+                status,
+                code: WechatErrorCode::Other(String::new()),
+                message: String::from_utf8_lossy(body).into_owned(),
+                detail: None,
+            },
+        }
+    }
+
+    /// 5xx、429以及系统错误、频率限制、银行系统异常可以使用相同参数重试
+    pub fn is_retryable(&self) -> bool {
+        self.status.is_server_error()
+            || self.status == StatusCode::TOO_MANY_REQUESTS
+            || matches!(
+                self.code,
+                WechatErrorCode::SystemError
+                    | WechatErrorCode::FrequencyLimited
+                    | WechatErrorCode::BankError
+            )
+    }
+}
+
+impl Display for WechatApiError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}: {}", self.status, self.code, self.message)?;
+        if let Some(detail) = &self.detail {
+            write!(
+                f,
+                " ({} {})",
+                detail.field.as_deref().unwrap_or_default(),
+                detail.issue.as_deref().unwrap_or_default()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// 【错误码】 未识别的错误码保留原始字符串
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WechatErrorCode {
+    /// 系统错误
+    SystemError,
+    /// 频率超限
+    FrequencyLimited,
+    /// 参数错误
+    ParamError,
+    /// 请求参数符合参数格式，但不符合业务规则
+    InvalidRequest,
+    /// 签名错误
+    SignError,
+    /// 商户无权限
+    NoAuth,
+    /// 订单已支付
+    OrderPaid,
+    /// 订单不存在
+    OrderNotExist,
+    /// 订单已关闭
+    OrderClosed,
+    /// 商户订单号重复
+    OutTradeNoUsed,
+    /// 余额不足
+    NotEnough,
+    /// 银行系统异常
+    BankError,
+    /// 查询的资源不存在
+    ResourceNotExists,
+    /// 用户支付中
+    UserPaying,
+    Other(String),
+}
+
+impl From<String> for WechatErrorCode {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "SYSTEM_ERROR" | "SYSTEMERROR" => WechatErrorCode::SystemError,
+            "FREQUENCY_LIMITED" => WechatErrorCode::FrequencyLimited,
+            "PARAM_ERROR" => WechatErrorCode::ParamError,
+            "INVALID_REQUEST" => WechatErrorCode::InvalidRequest,
+            "SIGN_ERROR" => WechatErrorCode::SignError,
+            "NO_AUTH" | "NOAUTH" => WechatErrorCode::NoAuth,
+            "ORDERPAID" | "ORDER_PAID" => WechatErrorCode::OrderPaid,
+            "ORDERNOTEXIST" | "ORDER_NOT_EXIST" => WechatErrorCode::OrderNotExist,
+            "ORDER_CLOSED" | "ORDERCLOSED" => WechatErrorCode::OrderClosed,
+            "OUT_TRADE_NO_USED" => WechatErrorCode::OutTradeNoUsed,
+            "NOTENOUGH" | "NOT_ENOUGH" => WechatErrorCode::NotEnough,
+            "BANKERROR" | "BANK_ERROR" => WechatErrorCode::BankError,
+            "RESOURCE_NOT_EXISTS" => WechatErrorCode::ResourceNotExists,
+            "USERPAYING" => WechatErrorCode::UserPaying,
+            _ => WechatErrorCode::Other(value),
+        }
+    }
+}
+
+impl Display for WechatErrorCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WechatErrorCode::SystemError => write!(f, "SYSTEM_ERROR"),
+            WechatErrorCode::FrequencyLimited => write!(f, "FREQUENCY_LIMITED"),
+            WechatErrorCode::ParamError => write!(f, "PARAM_ERROR"),
+            WechatErrorCode::InvalidRequest => write!(f, "INVALID_REQUEST"),
+            WechatErrorCode::SignError => write!(f, "SIGN_ERROR"),
+            WechatErrorCode::NoAuth => write!(f, "NO_AUTH"),
+            WechatErrorCode::OrderPaid => write!(f, "ORDERPAID"),
+            WechatErrorCode::OrderNotExist => write!(f, "ORDERNOTEXIST"),
+            WechatErrorCode::OrderClosed => write!(f, "ORDER_CLOSED"),
+            WechatErrorCode::OutTradeNoUsed => write!(f, "OUT_TRADE_NO_USED"),
+            WechatErrorCode::NotEnough => write!(f, "NOTENOUGH"),
+            WechatErrorCode::BankError => write!(f, "BANKERROR"),
+            WechatErrorCode::ResourceNotExists => write!(f, "RESOURCE_NOT_EXISTS"),
+            WechatErrorCode::UserPaying => write!(f, "USERPAYING"),
+            WechatErrorCode::Other(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::{PayError, WechatApiError, WechatErrorCode};
+    use http::StatusCode;
+
+    #[test]
+    fn test_wechat_api_error() {
+        let body = r#"{"code":"PARAM_ERROR","message":"参数错误","detail":{"field":"/amount/total","value":"0","issue":"must be greater than 0","location":"body"}}"#;
+        let error = WechatApiError::from_body(StatusCode::BAD_REQUEST, body.as_bytes());
+        assert_eq!(error.code, WechatErrorCode::ParamError);
+        assert_eq!(error.message, "参数错误");
+        let detail = error.detail.as_ref().unwrap();
+        assert_eq!(detail.field.as_deref(), Some("/amount/total"));
+        assert_eq!(detail.location.as_deref(), Some("body"));
+        assert!(!error.is_retryable());
+
+        let error = WechatApiError::from_body(
+            StatusCode::FORBIDDEN,
+            r#"{"code":"FREQUENCY_LIMITED","message":"频率超限"}"#.as_bytes(),
+        );
+        assert!(error.is_retryable());
+        assert!(PayError::from(error).is_retryable());
+
+        let error = WechatApiError::from_body(
+            StatusCode::BAD_REQUEST,
+            r#"{"code":"ORDERPAID","message":"订单已支付"}"#.as_bytes(),
+        );
+        assert_eq!(error.code, WechatErrorCode::OrderPaid);
+        assert_eq!(error.to_string(), "400 Bad Request ORDERPAID: 订单已支付");
+
+        let error = WechatApiError::from_body(StatusCode::BAD_GATEWAY, b"<html>bad gateway</html>");
+        assert_eq!(error.code, WechatErrorCode::Other(String::new()));
+        assert!(error.is_retryable());
+        assert_eq!(
+            WechatErrorCode::from("NEW_CODE".to_string()),
+            WechatErrorCode::Other("NEW_CODE".to_string())
+        );
+        // Display输出微信支付文档中的错误码
+        let code = WechatErrorCode::from("NOT_ENOUGH".to_string());
+        assert_eq!(code.to_string(), "NOTENOUGH");
+        assert_eq!(WechatErrorCode::from(code.to_string()), code);
+    }
+}
@@ -0,0 +1,308 @@
+//! 多域名容灾，主域名连接失败时切换到备用域名`api2.mch.weixin.qq.com`，幂等请求返回5xx时也会切换
+//!
+//! 每个域名有独立的熔断器：连续失败`failure_threshold`次后熔断，`open_duration`内不再优先请求该域名，
+//! 到期后只放行一个请求试探，成功即恢复，失败则重新熔断。请求签名只包含path，切换域名时无需重新签名
+use crate::debug;
+use crate::error::PayError;
+use crate::pay::{API_DOMAIN, BACKUP_API_DOMAIN};
+use crate::transport::{HttpRequest, HttpResponse, HttpTransport};
+use http::StatusCode;
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "async")]
+use maybe_async::maybe_async as maybe_async_attr;
+#[cfg(not(feature = "async"))]
+use maybe_async::must_be_sync as maybe_async_attr;
+
+/// 容灾事件，通过`Failover::with_listener`上报
+#[derive(Debug, Clone, PartialEq)]
+pub enum FailoverEvent<'a> {
+    /// 请求由domain完成，status为应答状态码
+    Served {
+        domain: &'a str,
+        path: &'a str,
+        status: StatusCode,
+    },
+    /// from请求失败，切换到to重发
+    Switched {
+        from: &'a str,
+        to: &'a str,
+        path: &'a str,
+        reason: &'a str,
+    },
+    /// domain连续失败，熔断
+    CircuitOpened { domain: &'a str },
+    /// 熔断的domain请求成功，恢复
+    CircuitClosed { domain: &'a str },
+}
+
+type Listener = Arc<dyn Fn(&FailoverEvent) + Send + Sync>;
+
+#[derive(Debug, Default)]
+struct Breaker {
+    failures: u32,
+    open_until: Option<Instant>,
+    /// 熔断到期后已有请求在试探
+    probing: bool,
+}
+
+/// 多域名容灾，按顺序请求未熔断的域名，所有域名都熔断时依次请求全部域名
+pub struct Failover {
+    domains: Vec<String>,
+    /// 连续失败多少次后熔断，默认3次
+    pub failure_threshold: u32,
+    /// 熔断时长，默认30秒
+    pub open_duration: Duration,
+    breakers: Mutex<Vec<Breaker>>,
+    listener: Option<Listener>,
+}
+
+impl Default for Failover {
+    fn default() -> Self {
+        Self::new(API_DOMAIN, BACKUP_API_DOMAIN)
+    }
+}
+
+impl Debug for Failover {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Failover")
+            .field("domains", &self.domains)
+            .field("failure_threshold", &self.failure_threshold)
+            .field("open_duration", &self.open_duration)
+            .field("breakers", &self.breakers)
+            .finish()
+    }
+}
+
+impl Failover {
+    pub fn new(primary: impl AsRef<str>, backup: impl AsRef<str>) -> Self {
+        let domains = [primary.as_ref(), backup.as_ref()]
+            .iter()
+            .map(|domain| domain.trim_end_matches('/').to_string())
+            .collect::<Vec<_>>();
+        Self {
+            breakers: Mutex::new(domains.iter().map(|_| Breaker::default()).collect()),
+            domains,
+            failure_threshold: 3,
+            open_duration: Duration::from_secs(30),
+            listener: None,
+        }
+    }
+
+    pub fn with_failure_threshold(mut self, failure_threshold: u32) -> Self {
+        self.failure_threshold = failure_threshold.max(1);
+        self
+    }
+
+    pub fn with_open_duration(mut self, open_duration: Duration) -> Self {
+        self.open_duration = open_duration;
+        self
+    }
+
+    /// 监听容灾事件，例如记录每次请求由哪个域名完成
+    pub fn with_listener(
+        mut self,
+        listener: impl Fn(&FailoverEvent) + Send + Sync + 'static,
+    ) -> Self {
+        self.listener = Some(Arc::new(listener));
+        self
+    }
+
+    /// 主域名
+    pub fn primary(&self) -> &str {
+        self.domains[0].as_str()
+    }
+
+    pub fn domains(&self) -> &[String] {
+        &self.domains
+    }
+
+    /// 下一次请求优先使用的域名
+    pub fn current_domain(&self) -> &str {
+        let (index, _) = self.candidates(Instant::now(), false)[0];
+        self.domains[index].as_str()
+    }
+
+    /// 按顺序返回可请求的域名下标及是否为试探请求，全部熔断时返回所有域名；
+    /// 熔断到期的域名只放行一个试探请求，claim为true时占用试探名额
+    fn candidates(&self, now: Instant, claim: bool) -> Vec<(usize, bool)> {
+        let mut breakers = self.breakers.lock().unwrap();
+        let mut candidates = Vec::new();
+        for (index, breaker) in breakers.iter_mut().enumerate() {
+            match breaker.open_until {
+                None => candidates.push((index, false)),
+                Some(until) if now >= until && !breaker.probing => {
+                    breaker.probing = claim;
+                    candidates.push((index, true));
+                }
+                Some(_) => {}
+            }
+        }
+        if candidates.is_empty() {
+            (0..self.domains.len())
+                .map(|index| (index, false))
+                .collect()
+        } else {
+            candidates
+        }
+    }
+
+    /// 归还未使用的试探名额
+    fn release_probe(&self, index: usize) {
+        self.breakers.lock().unwrap()[index].probing = false;
+    }
+
+    fn record_success(&self, index: usize) {
+        let closed = {
+            let mut breakers = self.breakers.lock().unwrap();
+            let breaker = &mut breakers[index];
+            breaker.failures = 0;
+            breaker.probing = false;
+            breaker.open_until.take().is_some()
+        };
+        if closed {
+            self.notify(FailoverEvent::CircuitClosed {
+                domain: self.domains[index].as_str(),
+            });
+        }
+    }
+
+    fn record_failure(&self, index: usize) {
+        let opened = {
+            let mut breakers = self.breakers.lock().unwrap();
+            let breaker = &mut breakers[index];
+            breaker.failures = breaker.failures.saturating_add(1);
+            breaker.probing = false;
+            let was_open = breaker.open_until.is_some();
+            if breaker.failures >= self.failure_threshold {
+                breaker.open_until = Some(Instant::now() + self.open_duration);
+            }
+            !was_open && breaker.open_until.is_some()
+        };
+        if opened {
+            self.notify(FailoverEvent::CircuitOpened {
+                domain: self.domains[index].as_str(),
+            });
+        }
+    }
+
+    fn notify(&self, event: FailoverEvent) {
+        debug!("failover: {:?}", event);
+        if let Some(listener) = &self.listener {
+            listener(&event);
+        }
+    }
+
+    /// 发送请求，request.url为path，连接失败时切换到下一个域名；
+    /// 返回5xx时请求可能已被处理，只有幂等请求(idempotent为true)才会切换域名重发
+    #[maybe_async_attr]
+    pub(crate) async fn send(
+        &self,
+        transport: &dyn HttpTransport,
+        request: HttpRequest,
+        idempotent: bool,
+    ) -> Result<HttpResponse, PayError> {
+        let path = request.url.as_str();
+        let candidates = self.candidates(Instant::now(), true);
+        let mut result = Err(PayError::TransportError(
+            "failover domains not configured".to_string(),
+        ));
+        let mut attempted = 0;
+        for (position, &(index, _)) in candidates.iter().enumerate() {
+            attempted = position + 1;
+            let domain = self.domains[index].as_str();
+            let mut attempt = request.clone();
+            attempt.url = format!("{}{}", domain, path);
+            result = transport.send(attempt).await;
+            let reason = match &result {
+                Ok(response) if response.status.is_server_error() => response.status.to_string(),
+                Err(e) if e.is_connect_error() => e.to_string(),
+                Ok(response) => {
+                    self.record_success(index);
+                    self.notify(FailoverEvent::Served {
+                        domain,
+                        path,
+                        status: response.status,
+                    });
+                    break;
+                }
+                Err(_) => {
+                    self.release_probe(index);
+                    break;
+                }
+            };
+            self.record_failure(index);
+            match candidates.get(position + 1) {
+                Some(&(next, _)) if idempotent || result.is_err() => {
+                    self.notify(FailoverEvent::Switched {
+                        from: domain,
+                        to: self.domains[next].as_str(),
+                        path,
+                        reason: reason.as_str(),
+                    })
+                }
+                _ => {
+                    if let Ok(response) = &result {
+                        self.notify(FailoverEvent::Served {
+                            domain,
+                            path,
+                            status: response.status,
+                        });
+                    }
+                    break;
+                }
+            }
+        }
+        for &(index, probe) in &candidates[attempted..] {
+            if probe {
+                self.release_probe(index);
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::failover::Failover;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_half_open_probe() {
+        let failover = Failover::new("https://primary", "https://backup")
+            .with_failure_threshold(1)
+            .with_open_duration(Duration::ZERO);
+        assert_eq!(
+            failover.candidates(Instant::now(), true),
+            [(0, false), (1, false)]
+        );
+        failover.record_failure(0);
+        // 熔断到期后只放行一个试探请求
+        assert_eq!(
+            failover.candidates(Instant::now(), true),
+            [(0, true), (1, false)]
+        );
+        assert_eq!(failover.candidates(Instant::now(), true), [(1, false)]);
+        assert_eq!(failover.current_domain(), "https://backup");
+        // 试探失败重新熔断，到期后可以再次试探
+        failover.record_failure(0);
+        assert_eq!(
+            failover.candidates(Instant::now(), true),
+            [(0, true), (1, false)]
+        );
+        // 未用到的试探名额会归还
+        failover.release_probe(0);
+        assert_eq!(failover.current_domain(), "https://primary");
+        assert_eq!(
+            failover.candidates(Instant::now(), true),
+            [(0, true), (1, false)]
+        );
+        failover.record_success(0);
+        assert_eq!(
+            failover.candidates(Instant::now(), true),
+            [(0, false), (1, false)]
+        );
+    }
+}
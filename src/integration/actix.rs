@@ -0,0 +1,123 @@
+use crate::integration::VerifiedNotification;
+use crate::model::NotifyReply;
+use crate::pay::WechatPay;
+use actix_web::body::BoxBody;
+use actix_web::dev::Payload;
+use actix_web::http::StatusCode;
+use actix_web::web::{Bytes, Data};
+use actix_web::{FromRequest, HttpRequest, HttpResponse, Responder, ResponseError};
+use serde::de::DeserializeOwned;
+use std::future::Future;
+use std::pin::Pin;
+
+/// 从app_data中取出`web::Data<WechatPay>`，读取原始body与`Wechatpay-*`头完成验签与解密
+impl<T> FromRequest for VerifiedNotification<T>
+where
+    T: DeserializeOwned + 'static,
+{
+    type Error = NotifyReply;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let wechat_pay = req.app_data::<Data<WechatPay>>().cloned();
+        let headers =
+            super::wechatpay_headers(|name| req.headers().get(name).map(|value| value.as_bytes()));
+        let bytes = Bytes::from_request(req, payload);
+        Box::pin(async move {
+            let wechat_pay =
+                wechat_pay.ok_or_else(|| NotifyReply::fail("WechatPay app data not found"))?;
+            let bytes = bytes.await.map_err(|e| NotifyReply::fail(e.to_string()))?;
+            let body = std::str::from_utf8(&bytes).map_err(|e| NotifyReply::fail(e.to_string()))?;
+            #[cfg(feature = "async")]
+            super::fetch_platform_pub_key(wechat_pay.as_ref(), &headers).await;
+            VerifiedNotification::verify(wechat_pay.as_ref(), &headers, body)
+                .map_err(|e| NotifyReply::fail(e.to_string()))
+        })
+    }
+}
+
+impl ResponseError for NotifyReply {
+    fn status_code(&self) -> StatusCode {
+        if self.is_success() {
+            StatusCode::OK
+        } else {
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code()).json(self)
+    }
+}
+
+impl Responder for NotifyReply {
+    type Body = BoxBody;
+
+    fn respond_to(self, _req: &HttpRequest) -> HttpResponse<Self::Body> {
+        self.error_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::integration::VerifiedNotification;
+    use crate::model::{NotifyReply, WechatPayDecodeData};
+    use crate::pay::tests::{notify_headers, transaction_notify_fixture};
+    use actix_web::body::to_bytes;
+    use actix_web::http::StatusCode;
+    use actix_web::test::TestRequest;
+    use actix_web::web::Data;
+    use actix_web::{FromRequest, Responder, ResponseError};
+
+    fn notify_request(
+        wechat_pay: &Data<crate::pay::WechatPay>,
+        headers: &http::header::HeaderMap,
+        body: &str,
+    ) -> TestRequest {
+        let mut request = TestRequest::post()
+            .uri("/pay/notify")
+            .app_data(wechat_pay.clone())
+            .set_payload(body.to_string());
+        for (name, value) in headers.iter() {
+            request = request.insert_header((name.as_str(), value.to_str().unwrap()));
+        }
+        request
+    }
+
+    #[tokio::test]
+    async fn test_verified_notification() {
+        let (wechat_pay, private_key, body) = transaction_notify_fixture();
+        let wechat_pay = Data::new(wechat_pay);
+        let now = chrono::Local::now().timestamp();
+        let headers = notify_headers(&private_key, now, body.as_str());
+
+        let (req, mut payload) =
+            notify_request(&wechat_pay, &headers, body.as_str()).to_http_parts();
+        let notification =
+            VerifiedNotification::<WechatPayDecodeData>::from_request(&req, &mut payload)
+                .await
+                .unwrap();
+        assert_eq!(notification.notify.event_type, "TRANSACTION.SUCCESS");
+        assert_eq!(
+            notification.data.out_trade_no,
+            "1217752501201407033233368018"
+        );
+
+        let tampered = body.replace("支付成功", "退款成功");
+        let (req, mut payload) =
+            notify_request(&wechat_pay, &headers, tampered.as_str()).to_http_parts();
+        let rejection =
+            VerifiedNotification::<WechatPayDecodeData>::from_request(&req, &mut payload)
+                .await
+                .unwrap_err();
+        let response = rejection.error_response();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let bytes = to_bytes(response.into_body()).await.unwrap();
+        assert!(String::from_utf8_lossy(&bytes).contains(r#""code":"FAIL""#));
+
+        let response = NotifyReply::success().respond_to(&req);
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(bytes, r#"{"code":"SUCCESS"}"#);
+    }
+}
@@ -0,0 +1,102 @@
+use crate::integration::VerifiedNotification;
+use crate::model::NotifyReply;
+use crate::pay::WechatPay;
+use axum::extract::{FromRef, FromRequest, Request};
+use axum::http::header::CONTENT_TYPE;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde::de::DeserializeOwned;
+use std::sync::Arc;
+
+/// 从state中取出`Arc<WechatPay>`，读取原始body与`Wechatpay-*`头完成验签与解密
+impl<S, T> FromRequest<S> for VerifiedNotification<T>
+where
+    Arc<WechatPay>: FromRef<S>,
+    S: Send + Sync,
+    T: DeserializeOwned,
+{
+    type Rejection = NotifyReply;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let wechat_pay = Arc::<WechatPay>::from_ref(state);
+        let headers =
+            super::wechatpay_headers(|name| req.headers().get(name).map(|value| value.as_bytes()));
+        let body = String::from_request(req, state)
+            .await
+            .map_err(|e| NotifyReply::fail(e.body_text()))?;
+        #[cfg(feature = "async")]
+        super::fetch_platform_pub_key(wechat_pay.as_ref(), &headers).await;
+        VerifiedNotification::verify(wechat_pay.as_ref(), &headers, body.as_str())
+            .map_err(|e| NotifyReply::fail(e.to_string()))
+    }
+}
+
+impl IntoResponse for NotifyReply {
+    fn into_response(self) -> Response {
+        let status = if self.is_success() {
+            StatusCode::OK
+        } else {
+            StatusCode::INTERNAL_SERVER_ERROR
+        };
+        (
+            status,
+            [(CONTENT_TYPE, "application/json")],
+            self.to_string(),
+        )
+            .into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::integration::VerifiedNotification;
+    use crate::model::{NotifyReply, WechatPayDecodeData};
+    use crate::pay::tests::{notify_headers, transaction_notify_fixture};
+    use axum::body::{to_bytes, Body};
+    use axum::extract::{FromRequest, Request};
+    use axum::http::StatusCode;
+    use axum::response::IntoResponse;
+    use std::sync::Arc;
+
+    fn notify_request(headers: &http::header::HeaderMap, body: &str) -> Request {
+        let mut builder = Request::builder().method("POST").uri("/pay/notify");
+        for (name, value) in headers.iter() {
+            builder = builder.header(name, value);
+        }
+        builder.body(Body::from(body.to_string())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_verified_notification() {
+        let (wechat_pay, private_key, body) = transaction_notify_fixture();
+        let state = Arc::new(wechat_pay);
+        let now = chrono::Local::now().timestamp();
+        let headers = notify_headers(&private_key, now, body.as_str());
+
+        let request = notify_request(&headers, body.as_str());
+        let notification: VerifiedNotification<WechatPayDecodeData> =
+            VerifiedNotification::from_request(request, &state)
+                .await
+                .unwrap();
+        assert_eq!(notification.notify.event_type, "TRANSACTION.SUCCESS");
+        assert_eq!(
+            notification.data.out_trade_no,
+            "1217752501201407033233368018"
+        );
+
+        let tampered = body.replace("支付成功", "退款成功");
+        let request = notify_request(&headers, tampered.as_str());
+        let rejection = VerifiedNotification::<WechatPayDecodeData>::from_request(request, &state)
+            .await
+            .unwrap_err();
+        let response = rejection.into_response();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert!(String::from_utf8_lossy(&bytes).contains(r#""code":"FAIL""#));
+
+        let response = NotifyReply::success().into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(bytes, r#"{"code":"SUCCESS"}"#);
+    }
+}
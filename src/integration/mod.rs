@@ -0,0 +1,72 @@
+#[cfg(feature = "actix-web")]
+pub mod actix;
+#[cfg(feature = "axum")]
+pub mod axum;
+
+use crate::error::PayError;
+use crate::model::WechatPayNotify;
+use crate::pay::PayNotifyTrait;
+use http::header::HeaderMap;
+use serde::de::DeserializeOwned;
+
+/// 已完成验签与解密的回调通知，可作为web框架的extractor使用
+#[derive(Debug, Clone)]
+pub struct VerifiedNotification<T> {
+    pub notify: WechatPayNotify,
+    pub data: T,
+}
+
+impl<T> VerifiedNotification<T>
+where
+    T: DeserializeOwned,
+{
+    pub(crate) fn verify<P>(
+        wechat_pay: &P,
+        headers: &HeaderMap,
+        body: &str,
+    ) -> Result<Self, PayError>
+    where
+        P: PayNotifyTrait,
+    {
+        let notify = wechat_pay.verify_notification(headers, body)?;
+        let data = wechat_pay.decrypt_resource(&notify)?;
+        Ok(Self { notify, data })
+    }
+}
+
+/// 从请求头中复制验签所需的四个`Wechatpay-*`头，`get`按头名称返回原始值
+#[cfg(any(feature = "axum", feature = "actix-web"))]
+pub(crate) fn wechatpay_headers<'a, F>(get: F) -> HeaderMap
+where
+    F: Fn(&str) -> Option<&'a [u8]>,
+{
+    let mut headers = HeaderMap::new();
+    for name in [
+        crate::pay::WECHATPAY_TIMESTAMP,
+        crate::pay::WECHATPAY_NONCE,
+        crate::pay::WECHATPAY_SIGNATURE,
+        crate::pay::WECHATPAY_SERIAL,
+    ] {
+        if let Some(value) = get(name).and_then(|value| http::HeaderValue::from_bytes(value).ok()) {
+            headers.insert(name, value);
+        }
+    }
+    headers
+}
+
+/// 回调使用未知的平台证书序列号时，验签前先由证书管理器重新下载平台证书
+#[cfg(feature = "async")]
+pub(crate) async fn fetch_platform_pub_key(
+    wechat_pay: &crate::pay::WechatPay,
+    headers: &HeaderMap,
+) {
+    let Some(serial_no) = headers
+        .get(crate::pay::WECHATPAY_SERIAL)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return;
+    };
+    if let Err(_e) = wechat_pay.fetch_platform_pub_key(serial_no).await {
+        crate::error!("fetch platform pub key error: {}", _e);
+    }
+}
@@ -1,11 +1,25 @@
 #![doc = include_str!("../README.md")]
 pub mod async_impl;
+pub mod bill;
+pub mod builder;
+pub mod certificate;
 pub mod error;
+pub mod failover;
+#[cfg(any(feature = "axum", feature = "actix-web"))]
+pub mod integration;
 pub(crate) mod macros;
 pub mod model;
 pub mod pay;
 pub mod pay_type;
+pub mod reconcile;
+pub mod registry;
 pub mod request;
 pub mod response;
+pub mod retry;
+pub mod secret;
+pub mod sensitive;
 pub mod sign;
+pub mod signer;
+pub mod transport;
 pub mod util;
+pub mod verify;
@@ -1,3 +1,6 @@
+use crate::error::PayError;
+use crate::response::RefundStatus;
+use crate::sensitive;
 use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter};
 
@@ -511,12 +514,119 @@ pub struct WechatPayDecodeData {
     pub trade_state: String,
     pub trade_state_desc: String,
     pub bank_type: String,
+    #[serde(default)]
     pub attach: String,
     pub success_time: String,
     pub payer: PayerInfo,
     pub amount: AmountInfo,
 }
 
+#[derive(Deserialize, Debug, Clone)]
+pub struct WechatPayRefundDecodeData {
+    /// 【直连商户号】 直连商户的商户号，由微信支付生成并下发。
+    pub mchid: String,
+    /// 【商户订单号】 返回的商户订单号
+    pub out_trade_no: String,
+    /// 【微信支付订单号】 微信支付订单号
+    pub transaction_id: String,
+    /// 【商户退款单号】 商户退款单号
+    pub out_refund_no: String,
+    /// 【微信支付退款单号】 微信退款单号
+    pub refund_id: String,
+    /// 【退款状态】 退款状态，枚举值：
+    /// SUCCESS：退款成功
+    /// CLOSED：退款关闭
+    /// ABNORMAL：退款异常，退款到银行发现用户的卡作废或者冻结了，导致原路退款银行卡失败，可前往【商户平台—>交易中心】，手动处理此笔退款
+    pub refund_status: RefundStatus,
+    /// 【退款成功时间】 退款成功时间，当前退款成功时才有此返回值
+    pub success_time: Option<String>,
+    /// 【退款入账账户】 取当前退款单的退款入账方。
+    pub user_received_account: String,
+    /// 【金额信息】 金额信息
+    pub amount: RefundNotifyAmount,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct RefundNotifyAmount {
+    /// 【订单金额】 订单总金额，单位为分，只能为整数
+    pub total: i32,
+    /// 【退款金额】 退款金额，币种的最小单位，只能为整数，不能超过原订单支付金额，如果有使用券，后台会按比例退。
+    pub refund: i32,
+    /// 【用户支付金额】 用户实际支付金额，单位为分，只能为整数
+    pub payer_total: i32,
+    /// 【用户退款金额】 退款给用户的金额，不包含所有优惠券金额
+    pub payer_refund: i32,
+}
+
+/// 回调通知的应答，成功时返回`{"code":"SUCCESS"}`，失败时返回`{"code":"FAIL","message":"失败"}`
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct NotifyReply {
+    pub code: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl NotifyReply {
+    pub fn success() -> Self {
+        Self {
+            code: "SUCCESS".to_string(),
+            message: None,
+        }
+    }
+
+    pub fn fail<S: AsRef<str>>(message: S) -> Self {
+        Self {
+            code: "FAIL".to_string(),
+            message: Some(message.as_ref().to_string()),
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.code == "SUCCESS"
+    }
+}
+
+impl Display for NotifyReply {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", serde_json::to_string(self).unwrap())
+    }
+}
+
+/// 验签并解密后的回调通知，按`event_type`区分
+#[derive(Debug, Clone)]
+pub enum WechatPayNotification {
+    /// 支付成功通知，event_type为`TRANSACTION.SUCCESS`
+    Transaction {
+        notify: WechatPayNotify,
+        data: WechatPayDecodeData,
+    },
+    /// 退款结果通知，event_type为`REFUND.SUCCESS`、`REFUND.ABNORMAL`或`REFUND.CLOSED`
+    Refund {
+        notify: WechatPayNotify,
+        data: WechatPayRefundDecodeData,
+    },
+    /// 其它类型的通知，可使用`decrypt_resource`自行解密
+    Unknown { notify: WechatPayNotify },
+}
+
+impl WechatPayNotification {
+    /// 按event_type将解密后的resource反序列化为对应的通知
+    pub(crate) fn from_plaintext(
+        notify: WechatPayNotify,
+        plaintext: &[u8],
+    ) -> Result<Self, PayError> {
+        if notify.event_type == "TRANSACTION.SUCCESS" {
+            let data = serde_json::from_slice(plaintext)?;
+            Ok(WechatPayNotification::Transaction { notify, data })
+        } else if notify.event_type.starts_with("REFUND.") {
+            let data = serde_json::from_slice(plaintext)?;
+            Ok(WechatPayNotification::Refund { notify, data })
+        } else {
+            Ok(WechatPayNotification::Unknown { notify })
+        }
+    }
+}
+
 #[derive(Serialize, Debug, Clone)]
 pub struct RefundsParams {
     /// 【微信支付订单号】 微信支付侧订单的唯一标识，订单支付成功后，查询订单和支付成功回调通知会返回该参数。
@@ -628,3 +738,134 @@ impl ParamsTrait for RefundsParams {
         serde_json::to_string(self).unwrap()
     }
 }
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AbnormalRefundType {
+    UserBankCard,
+    MerchantBankCard,
+}
+
+impl Display for AbnormalRefundType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AbnormalRefundType::UserBankCard => write!(f, "USER_BANK_CARD"),
+            AbnormalRefundType::MerchantBankCard => write!(f, "MERCHANT_BANK_CARD"),
+        }
+    }
+}
+
+/// 异常退款请求参数，bank_account与real_name传入明文，通过`sensitive::to_json`序列化时使用平台公钥加密
+#[derive(Serialize, Debug, Clone)]
+pub struct AbnormalRefundParams {
+    /// 【商户退款单号】 商户系统内部的退款单号，商户系统内部唯一，只能是数字、大小写字母_-|*@ ，同一退款单号多次请求只退一笔。
+    pub out_refund_no: String,
+    /// 【异常退款处理方式】 可选：退款至用户银行卡USER_BANK_CARD、退款至交易商户银行账户MERCHANT_BANK_CARD
+    pub r#type: AbnormalRefundType,
+    /// 【开户银行】 银行类型，采用字符串类型的银行标识，值列表详见银行类型。仅支持招行、交通银行、农行、建行、工商、中行、平安、浦发、中信、光大、民生、兴业、广发、邮储、宁波银行的借记卡。
+    /// 若退款至用户此字段必填。
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bank_type: Option<String>,
+    /// 【收款银行卡号】 用户的银行卡账号，明文，若退款至用户此字段必填。
+    #[serde(
+        serialize_with = "sensitive::encrypt_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub bank_account: Option<String>,
+    /// 【收款用户姓名】 收款用户姓名，明文，若退款至用户此字段必填。
+    #[serde(
+        serialize_with = "sensitive::encrypt_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub real_name: Option<String>,
+}
+
+impl AbnormalRefundParams {
+    pub fn new<S: AsRef<str>>(
+        out_refund_no: S,
+        r#type: AbnormalRefundType,
+        bank_type: Option<S>,
+        bank_account: Option<S>,
+        real_name: Option<S>,
+    ) -> Self {
+        Self {
+            out_refund_no: out_refund_no.as_ref().to_string(),
+            r#type,
+            bank_type: bank_type.map(|s| s.as_ref().to_string()),
+            bank_account: bank_account.map(|s| s.as_ref().to_string()),
+            real_name: real_name.map(|s| s.as_ref().to_string()),
+        }
+    }
+}
+
+/// 账单类型
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum BillType {
+    /// 返回当日所有订单信息（不含充值退款订单）
+    All,
+    /// 返回当日成功支付的订单（不含充值退款订单）
+    Success,
+    /// 返回当日退款订单（不含充值退款订单）
+    Refund,
+    /// 返回当日充值退款订单
+    RechargeRefund,
+    /// 返回个性化账单当日所有订单信息
+    AllSpecial,
+    /// 返回个性化账单当日成功支付的订单
+    SucSpecial,
+    /// 返回个性化账单当日退款订单
+    RefSpecial,
+}
+
+impl Display for BillType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BillType::All => write!(f, "ALL"),
+            BillType::Success => write!(f, "SUCCESS"),
+            BillType::Refund => write!(f, "REFUND"),
+            BillType::RechargeRefund => write!(f, "RECHARGE_REFUND"),
+            BillType::AllSpecial => write!(f, "ALL_SPECIAL"),
+            BillType::SucSpecial => write!(f, "SUC_SPECIAL"),
+            BillType::RefSpecial => write!(f, "REF_SPECIAL"),
+        }
+    }
+}
+
+/// 账单压缩类型
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TarType {
+    /// 返回格式为.gzip的压缩包账单
+    Gzip,
+}
+
+impl Display for TarType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TarType::Gzip => write!(f, "GZIP"),
+        }
+    }
+}
+
+/// 资金账户类型
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AccountType {
+    /// 基本账户
+    Basic,
+    /// 运营账户
+    Operation,
+    /// 手续费账户
+    Fees,
+}
+
+impl Display for AccountType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AccountType::Basic => write!(f, "BASIC"),
+            AccountType::Operation => write!(f, "OPERATION"),
+            AccountType::Fees => write!(f, "FEES"),
+        }
+    }
+}
@@ -1,32 +1,97 @@
+use crate::builder::WechatPayBuilder;
+use crate::certificate::PlatformCertificateManager;
 use crate::error::PayError;
-use crate::model::WechatPayDecodeData;
+use crate::failover::Failover;
+use crate::model::{
+    WechatPayDecodeData, WechatPayNotification, WechatPayNotify, WechatPayRefundDecodeData,
+};
 use crate::request::HttpMethod;
-use crate::response::SignData;
+use crate::retry::RetryPolicy;
+use crate::secret::{ApiV3Key, SecretString};
+use crate::signer::{RsaSigner, Signer};
+use crate::transport::HttpTransport;
+#[cfg(any(feature = "async", feature = "blocking"))]
+use crate::transport::ReqwestTransport;
+use crate::verify::{self, ResponseVerifier};
 use crate::{debug, sign, util};
 use aes_gcm::aead::{AeadMut, Payload};
 use aes_gcm::{aead::KeyInit, Aes256Gcm};
-use reqwest::header::{HeaderMap, ACCEPT, AUTHORIZATION, CONTENT_TYPE, USER_AGENT};
-use rsa::pkcs8::DecodePublicKey;
-use rsa::sha2::{Digest, Sha256};
-use rsa::{Pkcs1v15Sign, RsaPublicKey};
+use http::header::{HeaderMap, HeaderValue, ACCEPT, AUTHORIZATION, CONTENT_TYPE, USER_AGENT};
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::sync::Arc;
 use uuid::Uuid;
 
+#[cfg(feature = "async")]
+use maybe_async::maybe_async as maybe_async_attr;
+#[cfg(not(feature = "async"))]
+use maybe_async::must_be_sync as maybe_async_attr;
+
+pub const WECHATPAY_TIMESTAMP: &str = "Wechatpay-Timestamp";
+pub const WECHATPAY_NONCE: &str = "Wechatpay-Nonce";
+pub const WECHATPAY_SIGNATURE: &str = "Wechatpay-Signature";
+/// 微信支付公钥ID前缀，`Wechatpay-Serial`以此开头时使用公钥模式验签
+pub const PUB_KEY_ID_PREFIX: &str = "PUB_KEY_ID_";
+pub const WECHATPAY_SERIAL: &str = "Wechatpay-Serial";
+/// 微信支付接口域名
+pub const API_DOMAIN: &str = "https://api.mch.weixin.qq.com";
+/// 微信支付容灾备用域名
+pub const BACKUP_API_DOMAIN: &str = "https://api2.mch.weixin.qq.com";
+
 #[derive(Debug)]
 pub struct WechatPay {
     pub appid: String,
     pub mch_id: String,
-    pub private_key: String,
     pub serial_no: String,
-    pub v3_key: String,
+    /// APIv3密钥
+    pub v3_key: SecretString,
     pub notify_url: String,
     pub base_url: String,
+    /// 平台证书序列号 -> 平台公钥(PEM)，用于回调验签
+    pub platform_pub_keys: HashMap<String, String>,
+    /// 回调时间戳与本地时间允许的最大偏差，单位秒
+    pub notify_time_window: i64,
+    /// 接口应答验签器，未设置时按`Wechatpay-Serial`使用微信支付公钥或平台证书验签
+    pub response_verifier: Option<Arc<dyn ResponseVerifier>>,
+    /// 是否校验接口应答签名，默认开启，未配置平台证书、微信支付公钥或验签器时请求返回`SignatureError`
+    pub response_verification: bool,
+    /// 平台证书管理器，用于回调与应答验签
+    pub certificate_manager: Option<Arc<PlatformCertificateManager>>,
+    /// 微信支付公钥ID，形如`PUB_KEY_ID_...`
+    pub platform_public_key_id: Option<String>,
+    /// 微信支付公钥(PEM)，公钥模式下用于验签与敏感信息加密
+    pub platform_public_key: Option<String>,
+    /// http传输，默认为reqwest，未启用reqwest时需通过with_transport设置
+    pub transport: Option<Arc<dyn HttpTransport>>,
+    /// 请求头User-Agent，默认为`wechat-pay-rust-sdk/版本 (操作系统)`
+    pub user_agent: String,
+    /// 幂等请求的重试策略，默认不重试
+    pub retry_policy: RetryPolicy,
+    /// 多域名容灾，设置后按其中的域名发送请求，忽略base_url
+    pub failover: Option<Arc<Failover>>,
+    /// 商户签名器，默认为构造时解析好的商户私钥，私钥无效时为None
+    signer: Option<Arc<dyn Signer>>,
 }
 
-unsafe impl Send for WechatPay {}
+#[cfg(any(feature = "async", feature = "blocking"))]
+fn default_transport() -> Option<Arc<dyn HttpTransport>> {
+    Some(Arc::new(ReqwestTransport::new()))
+}
 
-unsafe impl Sync for WechatPay {}
+#[cfg(not(any(feature = "async", feature = "blocking")))]
+fn default_transport() -> Option<Arc<dyn HttpTransport>> {
+    None
+}
 
 pub trait PayNotifyTrait: WechatPayTrait {
+    /// 根据`Wechatpay-Serial`查找对应的平台公钥，默认未配置平台公钥，回调验签会失败
+    fn platform_pub_key(&self, _serial_no: &str) -> Option<String> {
+        None
+    }
+    /// 回调时间戳与本地时间允许的最大偏差，单位秒
+    fn notify_time_window(&self) -> i64 {
+        300
+    }
     fn verify_signature<S>(
         &self,
         pub_key: &str,
@@ -44,14 +109,54 @@ pub trait PayNotifyTrait: WechatPayTrait {
             nonce.as_ref(),
             body.as_ref()
         );
-        let pub_key = RsaPublicKey::from_public_key_pem(pub_key)
-            .map_err(|e| PayError::VerifyError(format!("public key parser error: {}", e)))?;
-        let hashed = Sha256::new().chain_update(message).finalize();
-        let signature = util::base64_decode(signature.as_ref())?;
-        let scheme = Pkcs1v15Sign::new::<Sha256>();
-        pub_key
-            .verify(scheme, &hashed, signature.as_slice())
-            .map_err(|e| PayError::VerifyError(e.to_string()))
+        sign::sha256_verify(pub_key, message.as_str(), signature.as_ref())
+    }
+    /// 从回调的http头与body中完成验签与时间戳校验，返回未解密的通知
+    fn verify_notification(
+        &self,
+        headers: &HeaderMap,
+        body: impl AsRef<str>,
+    ) -> Result<WechatPayNotify, PayError> {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .ok_or_else(|| PayError::VerifyError(format!("missing header {}", name)))
+        };
+        let timestamp = header(WECHATPAY_TIMESTAMP)?;
+        let nonce = header(WECHATPAY_NONCE)?;
+        let signature = header(WECHATPAY_SIGNATURE)?;
+        let serial_no = header(WECHATPAY_SERIAL)?;
+        let notify_time = timestamp
+            .parse::<i64>()
+            .map_err(|e| PayError::VerifyError(format!("timestamp parser error: {}", e)))?;
+        if (chrono::Local::now().timestamp() - notify_time).abs() > self.notify_time_window() {
+            return Err(PayError::VerifyError(format!(
+                "timestamp {} out of window",
+                timestamp
+            )));
+        }
+        let pub_key = self.platform_pub_key(serial_no).ok_or_else(|| {
+            PayError::VerifyError(format!("platform pub key {} not found", serial_no))
+        })?;
+        let body = body.as_ref();
+        self.verify_signature(pub_key.as_str(), timestamp, nonce, signature, body)?;
+        let notify: WechatPayNotify = serde_json::from_str(body)?;
+        Ok(notify)
+    }
+    /// 从回调的http头与body中完成验签、时间戳校验与解密
+    fn parse_notification(
+        &self,
+        headers: &HeaderMap,
+        body: impl AsRef<str>,
+    ) -> Result<WechatPayNotification, PayError> {
+        let notify = self.verify_notification(headers, body)?;
+        if notify.event_type == "TRANSACTION.SUCCESS" || notify.event_type.starts_with("REFUND.") {
+            let plaintext = self.decrypt_notify_resource(&notify)?;
+            WechatPayNotification::from_plaintext(notify, plaintext.as_slice())
+        } else {
+            Ok(WechatPayNotification::Unknown { notify })
+        }
     }
     fn decrypt_paydata<S>(
         &self,
@@ -66,6 +171,37 @@ pub trait PayNotifyTrait: WechatPayTrait {
         let data: WechatPayDecodeData = serde_json::from_slice(&plaintext)?;
         Ok(data)
     }
+    fn decrypt_refunddata<S>(
+        &self,
+        ciphertext: S,
+        nonce: S,
+        associated_data: S,
+    ) -> Result<WechatPayRefundDecodeData, PayError>
+    where
+        S: AsRef<str>,
+    {
+        let plaintext = self.decrypt_bytes(ciphertext, nonce, associated_data)?;
+        let data: WechatPayRefundDecodeData = serde_json::from_slice(&plaintext)?;
+        Ok(data)
+    }
+    /// 解密回调通知中的resource，按需反序列化成对应的结构
+    fn decrypt_resource<T>(&self, notify: &WechatPayNotify) -> Result<T, PayError>
+    where
+        T: DeserializeOwned,
+    {
+        let plaintext = self.decrypt_notify_resource(notify)?;
+        let data: T = serde_json::from_slice(&plaintext)?;
+        Ok(data)
+    }
+    /// 解密回调通知中的resource，返回明文
+    fn decrypt_notify_resource(&self, notify: &WechatPayNotify) -> Result<Vec<u8>, PayError> {
+        let resource = &notify.resource;
+        self.decrypt_bytes(
+            resource.ciphertext.as_str(),
+            resource.nonce.as_str(),
+            resource.associated_data.as_deref().unwrap_or_default(),
+        )
+    }
     fn decrypt_bytes<S>(
         &self,
         ciphertext: S,
@@ -80,10 +216,10 @@ pub trait PayNotifyTrait: WechatPayTrait {
                 "nonce length must be 12".to_string(),
             ));
         }
-        let v3_key = self.v3_key();
+        let v3_key = ApiV3Key::new(self.v3_key().expose_secret())
+            .map_err(|e| PayError::DecryptError(e.to_string()))?;
         let ciphertext = util::base64_decode(ciphertext.as_ref())?;
-        let aes_key = v3_key.as_bytes();
-        let mut cipher = Aes256Gcm::new(aes_key.into());
+        let mut cipher = Aes256Gcm::new(v3_key.as_bytes().into());
         let payload = Payload {
             msg: ciphertext.as_slice(),
             aad: associated_data.as_ref().as_bytes(),
@@ -98,44 +234,86 @@ pub trait PayNotifyTrait: WechatPayTrait {
 pub trait WechatPayTrait {
     fn appid(&self) -> String;
     fn mch_id(&self) -> String;
-    fn private_key(&self) -> String;
     fn serial_no(&self) -> String;
-    fn v3_key(&self) -> String;
+    fn v3_key(&self) -> &SecretString;
     fn notify_url(&self) -> String;
     fn base_url(&self) -> String;
-    fn rsa_sign(&self, content: impl AsRef<str>) -> String;
     fn now_timestamp(&self) -> String {
         chrono::Local::now().timestamp().to_string()
     }
     fn nonce_str(&self) -> String {
         Uuid::new_v4().to_string().replace("-", "").to_uppercase()
     }
+}
 
-    fn mut_sign_data<S>(&self, prefix: S, prepay_id: S) -> SignData
-    where
-        S: AsRef<str>,
-    {
-        let app_id = self.appid();
-        let now_time = self.now_timestamp();
-        let nonce_str = self.nonce_str();
-        let ext_str = format!(
-            "{prefix}{prepay_id}",
-            prefix = prefix.as_ref(),
-            prepay_id = prepay_id.as_ref()
-        );
-        let signed_str = self.rsa_sign(format!("{app_id}\n{now_time}\n{nonce_str}\n{ext_str}\n"));
-        SignData {
-            app_id,
-            sign_type: "RSA".into(),
-            package: ext_str,
-            nonce_str,
-            timestamp: now_time,
-            pay_sign: signed_str,
+impl PayNotifyTrait for WechatPay {
+    fn platform_pub_key(&self, serial_no: &str) -> Option<String> {
+        if serial_no.starts_with(PUB_KEY_ID_PREFIX) {
+            return match &self.platform_public_key_id {
+                Some(pub_key_id) if pub_key_id == serial_no => self.platform_public_key.clone(),
+                _ => None,
+            };
+        }
+        if let Some(pub_key) = self
+            .certificate_manager
+            .as_ref()
+            .and_then(|manager| manager.public_key_for(serial_no))
+            .or_else(|| self.platform_pub_keys.get(serial_no).cloned())
+        {
+            return Some(pub_key);
         }
+        self.certificate_manager
+            .as_ref()
+            .and_then(|manager| self.refresh_pub_key(manager, serial_no))
+    }
+
+    fn notify_time_window(&self) -> i64 {
+        self.notify_time_window
     }
 }
 
-impl PayNotifyTrait for WechatPay {}
+impl WechatPay {
+    /// 序列号未知时同步重新下载平台证书
+    #[cfg(not(feature = "async"))]
+    fn refresh_pub_key(
+        &self,
+        manager: &PlatformCertificateManager,
+        serial_no: &str,
+    ) -> Option<String> {
+        manager
+            .public_key_or_refresh(self, serial_no)
+            .inspect_err(|_e| {
+                crate::error!("refresh platform certificates error: {}", _e);
+            })
+            .ok()
+            .flatten()
+    }
+
+    /// 同步代码中无法等待下载，标记证书管理器在下次请求前重新下载，
+    /// 需要立即获取时使用`fetch_platform_pub_key`
+    #[cfg(feature = "async")]
+    fn refresh_pub_key(
+        &self,
+        manager: &PlatformCertificateManager,
+        _serial_no: &str,
+    ) -> Option<String> {
+        manager.invalidate();
+        None
+    }
+}
+
+/// 自定义验签器优先；`Wechatpay-Serial`为公钥ID时使用微信支付公钥，否则按平台证书序列号查找
+impl ResponseVerifier for WechatPay {
+    fn verify(&self, serial_no: &str, message: &str, signature: &str) -> Result<(), PayError> {
+        if let Some(verifier) = &self.response_verifier {
+            return verifier.verify(serial_no, message, signature);
+        }
+        let pub_key = self.platform_pub_key(serial_no).ok_or_else(|| {
+            PayError::SignatureError(format!("platform pub key {} not found", serial_no))
+        })?;
+        sign::sha256_verify(pub_key.as_str(), message, signature)
+    }
+}
 
 impl WechatPayTrait for WechatPay {
     fn appid(&self) -> String {
@@ -144,14 +322,11 @@ impl WechatPayTrait for WechatPay {
     fn mch_id(&self) -> String {
         self.mch_id.clone()
     }
-    fn private_key(&self) -> String {
-        self.private_key.clone()
-    }
     fn serial_no(&self) -> String {
         self.serial_no.clone()
     }
-    fn v3_key(&self) -> String {
-        self.v3_key.clone()
+    fn v3_key(&self) -> &SecretString {
+        &self.v3_key
     }
     fn notify_url(&self) -> String {
         self.notify_url.clone()
@@ -160,19 +335,24 @@ impl WechatPayTrait for WechatPay {
     fn base_url(&self) -> String {
         self.base_url.clone()
     }
-
-    fn rsa_sign(&self, content: impl AsRef<str>) -> String {
-        let private_key = self.private_key.as_ref();
-        sign::sha256_sign(private_key, content.as_ref())
-    }
 }
 
 impl WechatPay {
-    #[allow(dead_code)]
-    fn with_base_url(mut self, base_url: impl AsRef<str>) -> Self {
-        self.base_url = base_url.as_ref().to_string();
+    /// 使用builder创建，支持PEM字符串或文件路径、环境变量与TOML/JSON配置文件
+    pub fn builder() -> WechatPayBuilder {
+        WechatPayBuilder::default()
+    }
+
+    /// 设置接口域名，默认为`API_DOMAIN`，容灾时可使用`BACKUP_API_DOMAIN`
+    pub fn with_base_url(mut self, base_url: impl AsRef<str>) -> Self {
+        self.base_url = base_url.as_ref().trim_end_matches('/').to_string();
         self
     }
+
+    /// 不校验商户私钥与APIv3密钥，无效时直到签名或解密时才会报错
+    #[deprecated(
+        note = "use `WechatPay::try_new` or `WechatPay::builder`, which reject an invalid private key or APIv3 key"
+    )]
     pub fn new<S: AsRef<str>>(
         appid: S,
         mch_id: S,
@@ -180,40 +360,256 @@ impl WechatPay {
         serial_no: S,
         v3_key: S,
         notify_url: S,
+    ) -> Self {
+        let signer = RsaSigner::new(private_key.as_ref())
+            .ok()
+            .map(|signer| Arc::new(signer) as Arc<dyn Signer>);
+        Self::from_parts(
+            appid.as_ref(),
+            mch_id.as_ref(),
+            serial_no.as_ref(),
+            v3_key.as_ref(),
+            notify_url.as_ref(),
+            signer,
+        )
+    }
+
+    /// 创建并校验商户私钥(PKCS#8或PKCS#1 PEM)与APIv3密钥(32字节)，校验失败时返回`ConfigError`
+    pub fn try_new<S: AsRef<str>>(
+        appid: S,
+        mch_id: S,
+        private_key: S,
+        serial_no: S,
+        v3_key: S,
+        notify_url: S,
+    ) -> Result<Self, PayError> {
+        let signer = RsaSigner::new(private_key.as_ref())?;
+        ApiV3Key::new(v3_key.as_ref())?;
+        Ok(Self::from_parts(
+            appid.as_ref(),
+            mch_id.as_ref(),
+            serial_no.as_ref(),
+            v3_key.as_ref(),
+            notify_url.as_ref(),
+            Some(Arc::new(signer)),
+        ))
+    }
+
+    /// 使用自定义签名器创建，商户私钥保存在KMS、HSM或独立的签名服务中，不再保存在内存
+    pub fn new_with_signer<S: AsRef<str>>(
+        appid: S,
+        mch_id: S,
+        signer: impl Signer + 'static,
+        serial_no: S,
+        v3_key: S,
+        notify_url: S,
+    ) -> Self {
+        Self::from_parts(
+            appid.as_ref(),
+            mch_id.as_ref(),
+            serial_no.as_ref(),
+            v3_key.as_ref(),
+            notify_url.as_ref(),
+            Some(Arc::new(signer)),
+        )
+    }
+
+    pub(crate) fn from_parts(
+        appid: &str,
+        mch_id: &str,
+        serial_no: &str,
+        v3_key: &str,
+        notify_url: &str,
+        signer: Option<Arc<dyn Signer>>,
     ) -> Self {
         Self {
-            appid: appid.as_ref().to_string(),
-            mch_id: mch_id.as_ref().to_string(),
-            private_key: private_key.as_ref().to_string(),
-            serial_no: serial_no.as_ref().to_string(),
-            v3_key: v3_key.as_ref().to_string(),
-            notify_url: notify_url.as_ref().to_string(),
-            base_url: "https://api.mch.weixin.qq.com".to_string(),
+            appid: appid.to_string(),
+            mch_id: mch_id.to_string(),
+            serial_no: serial_no.to_string(),
+            v3_key: v3_key.into(),
+            notify_url: notify_url.to_string(),
+            base_url: API_DOMAIN.to_string(),
+            platform_pub_keys: HashMap::new(),
+            notify_time_window: 300,
+            response_verifier: None,
+            response_verification: true,
+            certificate_manager: None,
+            platform_public_key_id: None,
+            platform_public_key: None,
+            transport: default_transport(),
+            user_agent: format!(
+                "wechat-pay-rust-sdk/{} ({})",
+                env!("CARGO_PKG_VERSION"),
+                std::env::consts::OS
+            ),
+            retry_policy: RetryPolicy::none(),
+            failover: None,
+            signer,
+        }
+    }
+
+    /// 替换商户私钥，支持PKCS#8与PKCS#1的PEM或DER格式，私钥只保存在签名器中
+    pub fn with_private_key(mut self, private_key: impl AsRef<[u8]>) -> Result<Self, PayError> {
+        let signer = RsaSigner::new(private_key)?;
+        self.signer = Some(Arc::new(signer));
+        Ok(self)
+    }
+
+    /// 替换商户签名器
+    pub fn with_signer(mut self, signer: impl Signer + 'static) -> Self {
+        self.signer = Some(Arc::new(signer));
+        self
+    }
+
+    pub(crate) fn signer(&self) -> Result<&dyn Signer, PayError> {
+        self.signer.as_deref().ok_or_else(|| {
+            PayError::SignError(
+                "merchant private key is invalid or signer not configured".to_string(),
+            )
+        })
+    }
+
+    /// 添加平台公钥，serial_no为平台证书序列号
+    pub fn with_platform_pub_key(
+        mut self,
+        serial_no: impl AsRef<str>,
+        pub_key: impl AsRef<str>,
+    ) -> Self {
+        self.platform_pub_keys
+            .insert(serial_no.as_ref().to_string(), pub_key.as_ref().to_string());
+        self
+    }
+
+    pub fn with_notify_time_window(mut self, seconds: i64) -> Self {
+        self.notify_time_window = seconds;
+        self
+    }
+
+    /// 设置接口应答验签器，例如使用下载的平台证书或平台公钥验签
+    pub fn with_response_verifier(mut self, verifier: impl ResponseVerifier + 'static) -> Self {
+        self.response_verifier = Some(Arc::new(verifier));
+        self
+    }
+
+    /// 不校验接口应答签名，仅用于测试或由网关统一验签的场景
+    pub fn without_response_verification(mut self) -> Self {
+        self.response_verification = false;
+        self
+    }
+
+    /// 设置平台证书管理器，回调与应答验签时按序列号查找平台公钥
+    pub fn with_certificate_manager(mut self, manager: Arc<PlatformCertificateManager>) -> Self {
+        self.certificate_manager = Some(manager);
+        self
+    }
+
+    /// 设置http传输，例如自定义双向TLS、代理或连接池的client，测试时也可注入内存实现
+    pub fn with_transport(mut self, transport: impl HttpTransport + 'static) -> Self {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// 设置请求头User-Agent
+    pub fn with_user_agent(mut self, user_agent: impl AsRef<str>) -> Self {
+        self.user_agent = user_agent.as_ref().to_string();
+        self
+    }
+
+    /// 设置幂等请求(查询、关单、退款)的重试策略
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// 开启多域名容灾，主域名连接失败或返回5xx时切换到备用域名
+    pub fn with_failover(mut self, failover: Failover) -> Self {
+        self.base_url = failover.primary().to_string();
+        self.failover = Some(Arc::new(failover));
+        self
+    }
+
+    pub(crate) fn transport(&self) -> Result<&dyn HttpTransport, PayError> {
+        self.transport
+            .as_deref()
+            .ok_or_else(|| PayError::TransportError("http transport not configured".to_string()))
+    }
+
+    /// 设置微信支付公钥(公钥模式)，pub_key_id为商户平台上的公钥ID，形如`PUB_KEY_ID_...`
+    pub fn with_platform_public_key(
+        mut self,
+        pub_key_id: impl AsRef<str>,
+        pub_key: impl AsRef<str>,
+    ) -> Self {
+        self.platform_public_key_id = Some(pub_key_id.as_ref().to_string());
+        self.platform_public_key = Some(pub_key.as_ref().to_string());
+        self
+    }
+
+    /// 敏感信息加密使用的平台公钥，返回(`Wechatpay-Serial`, 公钥PEM)
+    ///
+    /// 优先使用微信支付公钥，其次使用证书管理器中已生效、未过期且最晚过期的平台证书，
+    /// 最后使用手动添加的平台公钥(没有有效期信息，取序列号最大的一个)；过期时间相同时同样取序列号最大的一个
+    pub fn platform_encrypt_key(&self) -> Result<(String, String), PayError> {
+        if let (Some(pub_key_id), Some(pub_key)) =
+            (&self.platform_public_key_id, &self.platform_public_key)
+        {
+            return Ok((pub_key_id.clone(), pub_key.clone()));
         }
+        let now = chrono::Local::now().timestamp();
+        if let Some(certificate) = self.certificate_manager.as_ref().and_then(|manager| {
+            manager
+                .certificates()
+                .into_iter()
+                .filter(|certificate| {
+                    certificate.effective_timestamp <= now && certificate.expire_timestamp > now
+                })
+                .max_by(|a, b| {
+                    (a.expire_timestamp, &a.serial_no).cmp(&(b.expire_timestamp, &b.serial_no))
+                })
+        }) {
+            return Ok((certificate.serial_no, certificate.pub_key));
+        }
+        self.platform_pub_keys
+            .iter()
+            .max_by_key(|(serial_no, _)| serial_no.as_str())
+            .map(|(serial_no, pub_key)| (serial_no.clone(), pub_key.clone()))
+            .ok_or_else(|| PayError::EncryptError("platform pub key not configured".to_string()))
+    }
+
+    /// 校验接口应答的`Wechatpay-Signature`，未配置验签器与平台公钥时返回`SignatureError`
+    pub(crate) fn verify_response(&self, headers: &HeaderMap, body: &str) -> Result<(), PayError> {
+        if !self.response_verification {
+            debug!("response verification disabled, skip verify");
+            return Ok(());
+        }
+        if self.response_verifier.is_none()
+            && self.certificate_manager.is_none()
+            && self.platform_pub_keys.is_empty()
+            && self.platform_public_key.is_none()
+        {
+            return Err(PayError::SignatureError(
+                "no platform certificate, public key or response verifier configured".to_string(),
+            ));
+        }
+        verify::verify_response_headers(self, headers, body)
     }
 
     #[cfg(feature = "debug-print")]
     pub fn open_debug(&self) {
-        std::env::set_var("RUST_LOG", "oss=debug");
+        unsafe { std::env::set_var("RUST_LOG", "oss=debug") };
         tracing_subscriber::fmt()
             .with_max_level(tracing::Level::DEBUG)
             .with_line_number(true)
             .init();
     }
 
-    pub fn from_env() -> Self {
-        let appid = std::env::var("WECHAT_APPID").expect("WECHAT_APPID not found");
-        let mch_id = std::env::var("WECHAT_MCH_ID").expect("WECHAT_MCH_ID not found");
-        let private_key =
-            std::env::var("WECHAT_PRIVATE_KEY").expect("WECHAT_PRIVATE_KEY not found");
-        let serial_no = std::env::var("WECHAT_SERIAL_NO").expect("WECHAT_SERIAL_NO not found");
-        let v3_key = std::env::var("WECHAT_V3_KEY").expect("WECHAT_V3_KEY not found");
-        let notify_url = std::env::var("WECHAT_NOTIFY_URL").expect("WECHAT_NOTIFY_URL not found");
-        let private_key = std::fs::read_to_string(private_key).expect("read private key error");
-        Self::new(appid, mch_id, private_key, serial_no, v3_key, notify_url)
+    /// 从环境变量读取商户配置，WECHAT_PRIVATE_KEY为商户私钥文件路径，支持的变量见`WechatPayBuilder::load_env`
+    pub fn from_env() -> Result<Self, PayError> {
+        Self::builder().load_env().build()
     }
 
-    pub(crate) fn build_header(
+    #[maybe_async_attr]
+    pub(crate) async fn build_header(
         &self,
         method: HttpMethod,
         url: impl AsRef<str>,
@@ -230,7 +626,7 @@ impl WechatPay {
             method, url, timestamp, nonce_str, body,
         );
         debug!("rsa_sign message: {}", message);
-        let signature = self.rsa_sign(message);
+        let signature = self.rsa_sign(message).await?;
         let authorization = format!(
             "WECHATPAY2-SHA256-RSA2048 mchid=\"{}\",nonce_str=\"{}\",signature=\"{}\",timestamp=\"{}\",serial_no=\"{}\"",
             self.mch_id,
@@ -241,22 +637,38 @@ impl WechatPay {
         );
         debug!("authorization: {}", authorization);
         let mut headers = HeaderMap::new();
-        headers.insert(ACCEPT, "application/json".parse().unwrap());
-        let chrome_agent = "Mozilla/5.0 (Linux; Android 10; Redmi K30 Pro) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/86.0.4240.198 Mobile Safari/537.36";
-        headers.insert(USER_AGENT, chrome_agent.parse().unwrap());
-        headers.insert(AUTHORIZATION, authorization.parse().unwrap());
-        headers.insert(CONTENT_TYPE, "application/json".parse().unwrap());
+        headers.insert(ACCEPT, HeaderValue::from_static("application/json"));
+        headers.insert(USER_AGENT, HeaderValue::from_str(self.user_agent.as_str())?);
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(authorization.as_str())?,
+        );
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
         Ok(headers)
     }
 }
 
 #[cfg(test)]
-mod tests {
-    use crate::pay::{PayNotifyTrait, WechatPay, WechatPayTrait};
+pub(crate) mod tests {
+    use crate::error::{ConfigError, PayError};
+    use crate::model::{
+        WechatPayNotification, WechatPayNotify, WechatPayNotifySource, WechatPayRefundDecodeData,
+    };
+    use crate::pay::{
+        PayNotifyTrait, WechatPay, WechatPayTrait, WECHATPAY_NONCE, WECHATPAY_SERIAL,
+        WECHATPAY_SIGNATURE, WECHATPAY_TIMESTAMP,
+    };
+    use crate::response::RefundStatus;
+    use crate::secret::SecretString;
+    use crate::util;
+    use aes_gcm::aead::{Aead, Payload};
+    use aes_gcm::{aead::KeyInit, Aes256Gcm};
     use dotenvy::dotenv;
-    use rsa::pkcs8::DecodePublicKey;
+    use http::header::HeaderMap;
+    use rsa::pkcs8::{DecodePublicKey, EncodePrivateKey, EncodePublicKey, LineEnding};
+    use rsa::rand_core::OsRng;
     use rsa::sha2::{Digest, Sha256};
-    use rsa::{Pkcs1v15Sign, RsaPublicKey};
+    use rsa::{Pkcs1v15Sign, RsaPrivateKey, RsaPublicKey};
     use tracing::debug;
     use uuid::Uuid;
 
@@ -269,12 +681,21 @@ mod tests {
     }
 
     #[test]
+    #[cfg(not(feature = "async"))]
     fn test_rsa_sign() {
         init_log();
         let private_key_path = "./apiclient_key.pem";
         let private_key = std::fs::read_to_string(private_key_path).unwrap();
-        let wechat_pay = WechatPay::new("", "", private_key.as_ref(), "", "", "");
-        let sign_str = wechat_pay.rsa_sign("hello");
+        let wechat_pay = WechatPay::try_new(
+            "",
+            "",
+            private_key.as_ref(),
+            "",
+            "0123456789abcdef0123456789abcdef",
+            "",
+        )
+        .unwrap();
+        let sign_str = wechat_pay.rsa_sign("hello").unwrap();
         debug!("sign_str: {}", sign_str);
     }
 
@@ -295,13 +716,294 @@ mod tests {
         let associated_data = "transaction";
         let nonce = "gZiqzlfayUu2";
         let ciphertext = "pCidqdiS5IIj5f9Pw9j69zuzu8l8IxcPCkfsTBKzna4gqZztNAqTMUY/Ai0rtj8qhaX0naYZF3a2lRid/ofK/83MNv+Neb5+w/0+UOO9nLNJvIFy3oFeMf2PTbp6tgDE35T5AoP9iKQ+1VkXTiUdRxzFoRx6/LfBzHmeuVEDHKScRqjrf6NdxuDDD0ciCQaiHmb18Y0BRZdfNxWTAC83Rar5yTX2NNZPBtGdFDG3yAK2I3Vp7ZKLeMa92ecExNGwHrdJ+HxWw66IIdwVqJLlNmTG0c5zUpSc8yovnaJi1Wv/TC7Tm5NzcwdHsdRE110tIWFbvNmIzIIb+3P33JFWmaXXb1VVDC43DqtlplttYwL6H3kU0ABgHMMbccTwYmP4cSY8BCAL01754nqipxWogEC/la9iQiw85+rLRo/Ny9k3mp8n35D6bDNtS1LiaslbLM92ZbfKeglTg54F/R1l5xWolAVpx8iTz8Oc+XJClXdWr8j5poyh8zK2/RrXPRfr+8s2/oGeGvdaqJbN/LviYcCMDbXU9pKDScWlSi4akxfJu0EatPDvFEbn5DYRQnn5v6wCeesYkEL+wiFCAIs=";
-        let wechat_pay = WechatPay::from_env();
+        let wechat_pay = WechatPay::from_env().unwrap();
         let data = wechat_pay
             .decrypt_paydata(ciphertext, nonce, associated_data)
             .unwrap();
         debug!("data: {:#?}", data);
     }
 
+    pub(crate) fn encrypt_resource(
+        v3_key: &str,
+        nonce: &str,
+        associated_data: &str,
+        plaintext: &str,
+    ) -> String {
+        let ciphertext = Aes256Gcm::new(v3_key.as_bytes().into())
+            .encrypt(
+                nonce.as_bytes().into(),
+                Payload {
+                    msg: plaintext.as_bytes(),
+                    aad: associated_data.as_bytes(),
+                },
+            )
+            .unwrap();
+        util::base64_encode(ciphertext)
+    }
+
+    pub(crate) fn notify_headers(
+        private_key: &RsaPrivateKey,
+        timestamp: i64,
+        body: &str,
+    ) -> HeaderMap {
+        let nonce = "Jh9oPZelCJIQeQ47kz4stzvDKpLEUhCX";
+        let message = format!("{}\n{}\n{}\n", timestamp, nonce, body);
+        let hashed = Sha256::new().chain_update(message).finalize();
+        let signature = private_key
+            .sign(Pkcs1v15Sign::new::<Sha256>(), &hashed)
+            .unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(WECHATPAY_TIMESTAMP, timestamp.to_string().parse().unwrap());
+        headers.insert(WECHATPAY_NONCE, nonce.parse().unwrap());
+        headers.insert(
+            WECHATPAY_SIGNATURE,
+            util::base64_encode(signature).parse().unwrap(),
+        );
+        headers.insert(WECHATPAY_SERIAL, "PLATFORM_SERIAL".parse().unwrap());
+        headers
+    }
+
+    /// 生成本地平台密钥，返回配置好公钥的WechatPay、平台私钥与加密后的支付成功回调body
+    #[allow(deprecated)]
+    pub(crate) fn transaction_notify_fixture() -> (WechatPay, RsaPrivateKey, String) {
+        let v3_key = "0123456789abcdef0123456789abcdef";
+        let private_key = RsaPrivateKey::new(&mut OsRng, 1024).unwrap();
+        let pub_key = RsaPublicKey::from(&private_key)
+            .to_public_key_pem(LineEnding::LF)
+            .unwrap();
+        let wechat_pay = WechatPay::new("", "", "", "", v3_key, "")
+            .with_platform_pub_key("PLATFORM_SERIAL", pub_key.as_str());
+        let plaintext = r#"{"mchid":"1900000100","appid":"wxd678efh567hg6787","out_trade_no":"1217752501201407033233368018","transaction_id":"1217752501201407033233368018","trade_type":"JSAPI","trade_state":"SUCCESS","trade_state_desc":"支付成功","bank_type":"CMC","success_time":"2018-06-08T10:34:56+08:00","payer":{"openid":"oUpF8uMuAJO_M2pxb1Q9zNjWeS6o"},"amount":{"total":100}}"#;
+        let ciphertext = encrypt_resource(v3_key, "uaGeNnBYNjl7", "transaction", plaintext);
+        let body = format!(
+            r#"{{"id":"EV-2018022511223320873","create_time":"2018-06-08T10:34:56+08:00","resource_type":"encrypt-resource","event_type":"TRANSACTION.SUCCESS","summary":"支付成功","resource":{{"original_type":"transaction","algorithm":"AEAD_AES_256_GCM","ciphertext":"{}","associated_data":"transaction","nonce":"uaGeNnBYNjl7"}}}}"#,
+            ciphertext
+        );
+        (wechat_pay, private_key, body)
+    }
+
+    /// 只实现WechatPayTrait的自定义类型，平台公钥使用PayNotifyTrait的默认实现
+    struct DefaultNotify(WechatPay);
+
+    impl WechatPayTrait for DefaultNotify {
+        fn appid(&self) -> String {
+            self.0.appid()
+        }
+        fn mch_id(&self) -> String {
+            self.0.mch_id()
+        }
+        fn serial_no(&self) -> String {
+            self.0.serial_no()
+        }
+        fn v3_key(&self) -> &SecretString {
+            self.0.v3_key()
+        }
+        fn notify_url(&self) -> String {
+            self.0.notify_url()
+        }
+        fn base_url(&self) -> String {
+            self.0.base_url()
+        }
+    }
+
+    impl PayNotifyTrait for DefaultNotify {}
+
+    #[test]
+    fn test_default_platform_pub_key() {
+        let (wechat_pay, platform_key, body) = transaction_notify_fixture();
+        let headers = notify_headers(&platform_key, chrono::Local::now().timestamp(), &body);
+        let notify = DefaultNotify(wechat_pay);
+        assert!(notify.platform_pub_key("PLATFORM_SERIAL").is_none());
+        let error = notify
+            .verify_notification(&headers, body.as_str())
+            .unwrap_err();
+        assert!(matches!(error, PayError::VerifyError(_)));
+    }
+
+    /// 回调验签、时间戳校验并解密
+    #[test]
+    fn test_parse_notification() {
+        let (wechat_pay, private_key, body) = transaction_notify_fixture();
+        let now = chrono::Local::now().timestamp();
+        let headers = notify_headers(&private_key, now, body.as_str());
+        match wechat_pay
+            .parse_notification(&headers, body.as_str())
+            .unwrap()
+        {
+            WechatPayNotification::Transaction { data, .. } => {
+                assert_eq!(data.trade_state, "SUCCESS");
+                assert_eq!(data.amount.total, 100);
+            }
+            other => panic!("unexpected notification: {:?}", other),
+        }
+
+        let tampered = body.replace("TRANSACTION.SUCCESS", "REFUND.SUCCESS");
+        assert!(wechat_pay
+            .parse_notification(&headers, tampered.as_str())
+            .is_err());
+
+        let headers = notify_headers(&private_key, now - 600, body.as_str());
+        assert!(wechat_pay
+            .parse_notification(&headers, body.as_str())
+            .is_err());
+    }
+
+    /// 未配置平台公钥时应答验签失败，显式关闭后跳过
+    #[test]
+    #[allow(deprecated)]
+    fn test_verify_response_fail_closed() {
+        let wechat_pay = WechatPay::new("", "", "", "", "", "");
+        assert!(matches!(
+            wechat_pay.verify_response(&HeaderMap::new(), ""),
+            Err(PayError::SignatureError(_))
+        ));
+        assert!(wechat_pay
+            .without_response_verification()
+            .verify_response(&HeaderMap::new(), "")
+            .is_ok());
+    }
+
+    /// 私钥无法解析时返回错误而不是panic
+    #[test]
+    #[allow(deprecated)]
+    fn test_invalid_private_key() {
+        let error = WechatPay::try_new("appid", "mch_id", "not a key", "serial", "v3", "notify")
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            PayError::ConfigError(ConfigError::InvalidPrivateKey(_))
+        ));
+        let private_key = RsaPrivateKey::new(&mut OsRng, 1024)
+            .unwrap()
+            .to_pkcs8_der()
+            .unwrap();
+        let wechat_pay = WechatPay::new("appid", "mch_id", "not a key", "serial", "v3", "notify")
+            .with_private_key(private_key.as_bytes())
+            .unwrap();
+        assert!(wechat_pay.signer().is_ok());
+        // Debug输出不包含私钥与APIv3密钥
+        let debug = format!("{:?}", wechat_pay);
+        assert!(!debug.contains("BEGIN PRIVATE KEY"));
+        assert!(debug.contains("v3_key: SecretString(***)"));
+    }
+
+    /// APIv3密钥必须为32字节，长度错误时解密返回错误而不是panic
+    #[test]
+    #[allow(deprecated)]
+    fn test_invalid_v3_key() {
+        let private_key = RsaPrivateKey::new(&mut OsRng, 1024)
+            .unwrap()
+            .to_pkcs8_pem(LineEnding::LF)
+            .unwrap();
+        let error = WechatPay::try_new(
+            "appid",
+            "mch_id",
+            private_key.as_str(),
+            "serial",
+            "short_key",
+            "notify",
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            PayError::ConfigError(ConfigError::InvalidV3Key(9))
+        ));
+        let wechat_pay = WechatPay::new("", "", "", "", "short_key", "");
+        let result = wechat_pay.decrypt_bytes("cGF5bG9hZA==", "uaGeNnBYNjl7", "transaction");
+        assert!(matches!(result, Err(PayError::DecryptError(_))));
+    }
+
+    /// 公钥模式：按`Wechatpay-Serial`选择微信支付公钥验签，敏感信息加密使用公钥ID
+    #[test]
+    fn test_platform_public_key_mode() {
+        let (wechat_pay, certificate_key, body) = transaction_notify_fixture();
+        let public_key = RsaPrivateKey::new(&mut OsRng, 1024).unwrap();
+        let pub_key = RsaPublicKey::from(&public_key)
+            .to_public_key_pem(LineEnding::LF)
+            .unwrap();
+        let wechat_pay = wechat_pay.with_platform_public_key("PUB_KEY_ID_0114", pub_key.as_str());
+        let now = chrono::Local::now().timestamp();
+
+        let mut headers = notify_headers(&public_key, now, body.as_str());
+        headers.insert(WECHATPAY_SERIAL, "PUB_KEY_ID_0114".parse().unwrap());
+        assert!(wechat_pay
+            .verify_notification(&headers, body.as_str())
+            .is_ok());
+        assert!(wechat_pay.verify_response(&headers, body.as_str()).is_ok());
+
+        // 公钥ID不匹配时不会退回到平台证书
+        headers.insert(WECHATPAY_SERIAL, "PUB_KEY_ID_0115".parse().unwrap());
+        assert!(wechat_pay.verify_response(&headers, body.as_str()).is_err());
+
+        // 平台证书序列号仍使用证书模式验签
+        let headers = notify_headers(&certificate_key, now, body.as_str());
+        assert!(wechat_pay.verify_response(&headers, body.as_str()).is_ok());
+
+        let (serial_no, encrypt_key) = wechat_pay.platform_encrypt_key().unwrap();
+        assert_eq!(serial_no, "PUB_KEY_ID_0114");
+        assert_eq!(encrypt_key, pub_key);
+    }
+
+    /// 多个手动添加的平台公钥时固定选择序列号最大的一个
+    #[test]
+    #[allow(deprecated)]
+    fn test_platform_encrypt_key() {
+        let wechat_pay = WechatPay::new("", "", "", "", "", "");
+        assert!(matches!(
+            wechat_pay.platform_encrypt_key(),
+            Err(PayError::EncryptError(_))
+        ));
+        let wechat_pay = ["5157F09E", "7A1B2C3D", "6C4D5E6F"].iter().fold(
+            wechat_pay,
+            |wechat_pay, serial_no| {
+                wechat_pay.with_platform_pub_key(serial_no, format!("pub key {}", serial_no))
+            },
+        );
+        for _ in 0..10 {
+            let (serial_no, pub_key) = wechat_pay.platform_encrypt_key().unwrap();
+            assert_eq!(serial_no, "7A1B2C3D");
+            assert_eq!(pub_key, "pub key 7A1B2C3D");
+        }
+    }
+
+    /// 退款回调参数解密
+    #[test]
+    #[allow(deprecated)]
+    fn test_decrypt_refund_resource() {
+        let v3_key = "0123456789abcdef0123456789abcdef";
+        let wechat_pay = WechatPay::new("", "", "", "", v3_key, "");
+        let plaintext = r#"{"mchid":"1900000100","transaction_id":"1008450740201411110005820873","out_trade_no":"20150806125346","refund_id":"50200207182018070300011301001","out_refund_no":"7752501201407033233368018","refund_status":"SUCCESS","success_time":"2018-06-08T10:34:56+08:00","user_received_account":"招商银行信用卡0403","amount":{"total":999,"refund":999,"payer_total":999,"payer_refund":999}}"#;
+        let nonce = "fdasflkja484";
+        let associated_data = "refund";
+        let ciphertext = encrypt_resource(v3_key, nonce, associated_data, plaintext);
+        let notify = WechatPayNotify {
+            id: "EV-2018022511223320873".to_string(),
+            create_time: "2018-06-08T10:34:56+08:00".to_string(),
+            event_type: "REFUND.SUCCESS".to_string(),
+            resource_type: "encrypt-resource".to_string(),
+            resource: WechatPayNotifySource {
+                algorithm: "AEAD_AES_256_GCM".to_string(),
+                ciphertext,
+                associated_data: Some(associated_data.to_string()),
+                original_type: "refund".to_string(),
+                nonce: nonce.to_string(),
+            },
+            summary: "退款成功".to_string(),
+        };
+        let data: WechatPayRefundDecodeData = wechat_pay.decrypt_resource(&notify).unwrap();
+        assert_eq!(data.refund_status, RefundStatus::Success);
+        assert_eq!(data.amount.payer_refund, 999);
+        let resource = notify.resource;
+        let data = wechat_pay
+            .decrypt_refunddata(
+                resource.ciphertext,
+                resource.nonce,
+                resource.associated_data.unwrap_or_default(),
+            )
+            .unwrap();
+        assert_eq!(data.refund_id, "50200207182018070300011301001");
+    }
+
     /// 把微信支付平台证书序列号转换成16进制字符串
     /// ```text
     /// -----BEGIN CERTIFICATE-----
@@ -315,7 +1017,7 @@ mod tests {
         let associated_data = "certificate";
         let nonce = "bf003ed52d71";
         let ciphertext = "HE9tL+x8Mag2627GPRXBmaQxZPVhAm3f2UoxgHvVW+m6eN0vq6ggFf4UsaQ8ifeGKwjhj9M6ObREHNogrT5JlEDV4Mfg8pAcLNvKUnbQZeBFKtp8kXPy0KFGhfSWcMZ4+HyfAUkgqLpdRUuNpG3gSJptnfrbJktdtYifkDOcei+1ncq8x+aWCXkFw8l9xBSN8MVSf66TiKyuPD/QCKYbD92HHfmDHk2b8J+BKyISDlQTlKjpb9M01EnuPsIXi4Rww1YzZP8XDruRTFxxDxGmk74tu1cjGXzTIcNmFu85eHbLWENvoLttl/4cKLJ8w49PuCyrREACz1YeAOscEHsqYHaQ0VE2N/8J0wCBuQa+AVD6ra59lmCxRJOVfgQNTShxonA6uCfaPGtyg+5qlwYTESnSdIy2ODlXaOfzMT5N7/actJsEf2C7RJXTPWn79M5slVfE3gOh9aR3mJaEMFM9KZywqv4OT0OI9mpLqRLAV/QCkJ0q2SKCcZyIuLa+VAPVS5Rh2feQkP40iizvVPN68YMOAmVgMBYLaxehGnetT2UylTlqsov35hsbfKOEN5ArSr7y4xoTjW5BV4S0s2IDzHTHWQpMlTxJ59/sgMoq8+m8vezJ0W4AZubwG/iSQ+/tzv1CXAVUgMO8ZqEALpGiROVq+9hdD5a0UB6cGuOTw9OiQHLSn1M4zV2jWDLQSZ+Q8KFhTpMibnvdLFmC09k26K75VcACsNPSa9U+nvP9sp3H7a39Y9BXjIz8/Yd707Y8h76MpEWLsVTn7FvRWwaCi4vxZN/LMRh9KTLNffQcb5amoDYKVSr5BTshdM7EosNwQmGenNnAFlNE/mabXSIz+FC3gMlDbxVvaoB5vOLB/YHrqfoLMEtYGm2HGqjppLmkbNM/R/6NIDFe+jaXZPWh9Bt8F4blihJnbEsZlC/w0/2OylTUsjRipG443XhOLEZJgD54KOnQdpqDah+AW2tPq5V9528ePK5xJzZ33MB3kjgnmljaF5cVbgUcCp5e8N+zvFVoyltsYMNNrtOan0Zfpsj9hNPnUVKLEnsjGXyfpBazRKoOOrPK5MImLUt/JblT+PFZ1oSrQE1IRRfF88yaUYwY2qk3pTrqBY676hOIUesWwuN4CSm28lLu/VarJaY0iLKuoGF0eikGFnAae1BIuFxvDUc3C+vC4GXUFn9jr3PZQcGJuI3MbEk8xGFWcU8UBU2wWhRu5lIgFSX5krbe+FWmRSjl6Rc3s7HZi5Xa8RiRuN1bOcnhVYkNYXy1fg7lXoopWJJPgtMO/+DDTNGQe8G0UgQxy+OK0urlhtzGQjVhF838i0heG4JV+OWUKj/Qvoj/dxVVfIbfroupkg8GvMmn0Cq+nAuo0D0fvhQshDmRsL/a006piEiLthruMn/gymk8cccMVvzn+DxYfYH/WX2UKZ235hPynVLUo8FBBedVTQK3JuJHCT4Kz0lL28KRLpE+lW3/bzG9s0Bly7/h1BF5Xunv4TWYhMFseWGMRIiKR7HxMSXbD4Q1PQJrZt/DtP3JbPURfc6fuYPIb7iuka0kDkPGSCV2uCpzjVHZXYQWrDhFv7LWi4SUw+2mCZLsLR6kesexb5bBOMVRxnA/5WmYVp73WzXar28CW3l0WCccGL/EdVdhrx09RoW5GSy9zcjbyGhwZQuzZECbf/wCpd26YlMTzFP0bqfL/QJ4g32TX8XweyhTPRI7FX1Bg8x85GJYG/bvecR40lDj4A0WKGnVbic3e7LQpDi/BP9adDBxx3Nl0iCN9BUlMx6ypNmrQWHwQXgmPwapyByK0FHjmf0u7hExZ7+xMa9/DPo2YPJdAY6zuHlNUIXLEVa9/VrclsYbyGkeohFGsMgY5MIA0ZF5FFxEOQ31gtNgQiGIVywSGJS8L+qB3tDc07O8hMxCY9wKPP2ua0MkkKQ7O6cr3W1DxNsd9NCbENDW4zNHzT+4pafS1TFaEy0nHI/wIQEyJlXD";
-        let wechat_pay = WechatPay::from_env();
+        let wechat_pay = WechatPay::from_env().unwrap();
         let data = wechat_pay
             .decrypt_bytes(ciphertext, nonce, associated_data)
             .unwrap();
@@ -326,7 +1028,7 @@ mod tests {
     fn test_verify_sign() {
         init_log();
         dotenv().ok();
-        let wechat_pay = WechatPay::from_env();
+        let wechat_pay = WechatPay::from_env().unwrap();
         let pub_key = std::fs::read_to_string("pub.pem").unwrap();
         let wechatpay_signature = "mFgmwXAKL3YJj34b7f+cUG3vkW09TiXU4lOSzCbvWFtvyLTb5WiyfAiVXZmMB17Qh9gDVkqboO97zfIYfv+AVdxj3GQljWlW+vE1Ujn2uxiFld6bWwz8Znk+833ruzZ8mAIaqLEjI/HKuVPdTj4LFzh/EO+gEMR6WDXr+7cZV7D3qUTXuO26fHLe0PmleDziG8SPgYjihK1ztF3Os0NhvL5tQMM8LKDOMzO3kxSr/TqTBtsB/OnuP2mH8yaSUeYeTpGStYvSw8KVi+gk6VnrlkVmdFh3DDXY60GCzCZ8zPl12RmzZbBRSK8ocVrzs4tuqRa5Euk3cDIA6qHqS8hyBQ==";
         let body = r#"{"id":"29a61973-babf-599a-966d-6bcdcf17360c","create_time":"2024-01-12T21:39:44+08:00","resource_type":"encrypt-resource","event_type":"TRANSACTION.SUCCESS","summary":"支付成功","resource":{"original_type":"transaction","algorithm":"AEAD_AES_256_GCM","ciphertext":"5ZfDK+LRJakAkC7kdHKRzCu5WZ0JFC2qSwP4InWNFeUnY0uaOnzfCjiqhDTFYyP4ywxuLxPUOiVI3WT6CcU0NNqbadTQ5XzjVuKLxYSnOYCFULltIrfsT/mUF4VW+xBMgSgG4+ZdzhRXVr+AzihDKFjw2p1iCtLYz9emgToctygNBtV6JDEI2BnCoiEM7qyIU1ALv5IsufQHDQqzjYXd16OD3i6O8UeSE2GOd4ifmQrAKGKalwWPECI73/qTFoAcLcgbhhn1TeSEaHoF7xceDmkL9AGlC21pBwYWoibTgqdlDJiz3IctrCzH6PPXD8XcApEj4A3ByyPjaNs6HxaJGzEHYGUkyM2/b7SzZIzqlBmNRZYFvBC0BOwoktyxrIhg3bKSbYtDYt1+8lMaYIJW6Dgq9GjG6pxAVrYULt8sk8cKZ+OrK9iXHZI11pYyK9YwWJLXbs6GyjMdDxhaGilF9csK8ZSsKzUjvlcLCjboCFX6nuHvCbswchYchQhTeitKDKG3/q+4snY183dBA6rXBHKQduqc1vXRR6odMcU1Evvy5mKnDTDELlI6mqvBtJ10XNED5O43ga5ZAODxYoU=","associated_data":"transaction","nonce":"uaGeNnBYNjl7"}}"#;
@@ -0,0 +1,454 @@
+//! 本地对账，比较交易账单与商户自己的订单记录
+use crate::bill::TradeBillRow;
+use crate::error::PayError;
+use crate::response::{RefundStatus, RefundsResponse, TransactionResponse};
+use chrono::NaiveDate;
+use std::collections::{BTreeMap, HashSet};
+
+/// 商户本地的订单记录，金额单位为分
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalOrder {
+    /// 【商户订单号】
+    pub out_trade_no: String,
+    /// 【订单金额】
+    pub total: i64,
+    /// 【交易状态】 取值同`trade_state`，如SUCCESS、REFUND、NOTPAY、CLOSED、REVOKED
+    pub trade_state: String,
+    /// 【退款记录】 已成功的退款
+    pub refunds: Vec<LocalRefund>,
+}
+
+impl LocalOrder {
+    /// 本地是否认为已支付，已支付后转入退款的订单也视为已支付
+    pub fn is_paid(&self) -> bool {
+        matches!(self.trade_state.as_str(), "SUCCESS" | "REFUND")
+    }
+}
+
+impl From<&TransactionResponse> for LocalOrder {
+    fn from(transaction: &TransactionResponse) -> Self {
+        Self {
+            out_trade_no: transaction.out_trade_no.clone(),
+            total: transaction
+                .amount
+                .as_ref()
+                .and_then(|amount| amount.total)
+                .unwrap_or_default() as i64,
+            trade_state: transaction.trade_state.clone(),
+            refunds: vec![],
+        }
+    }
+}
+
+/// 商户本地的退款记录，金额单位为分
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalRefund {
+    /// 【商户退款单号】
+    pub out_refund_no: String,
+    /// 【退款金额】
+    pub refund: i64,
+}
+
+impl From<&RefundsResponse> for LocalRefund {
+    fn from(refund: &RefundsResponse) -> Self {
+        Self {
+            out_refund_no: refund.out_refund_no.clone(),
+            refund: refund.amount.refund as i64,
+        }
+    }
+}
+
+/// 本地订单数据源，由商户实现
+pub trait OrderSource {
+    /// 按商户订单号查询本地订单，账单中的退款可能对应之前日期的订单
+    fn order(&self, out_trade_no: &str) -> Option<LocalOrder>;
+    /// 查询指定日期(yyyy-MM-dd)支付成功的本地订单，用于发现微信侧缺失的订单
+    fn paid_orders(&self, date: &str) -> Vec<LocalOrder>;
+}
+
+/// 对账差异，金额单位为分
+#[derive(Debug, Clone, PartialEq)]
+pub enum Discrepancy {
+    /// 微信账单有记录，本地无此订单
+    MissingLocal { out_trade_no: String, wechat: i64 },
+    /// 本地已支付，微信账单无支付记录
+    MissingWechat { out_trade_no: String, local: i64 },
+    /// 订单金额不一致
+    AmountMismatch {
+        out_trade_no: String,
+        local: i64,
+        wechat: i64,
+    },
+    /// 退款不一致，本地无此退款时local为None
+    RefundMismatch {
+        out_trade_no: String,
+        out_refund_no: String,
+        local: Option<i64>,
+        wechat: i64,
+    },
+    /// 交易状态不一致
+    StatusMismatch {
+        out_trade_no: String,
+        local: String,
+        wechat: String,
+    },
+}
+
+/// 单日对账结果，金额单位为分
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DailyReconcile {
+    /// 账单日期 yyyy-MM-dd
+    pub date: String,
+    /// 账单支付笔数
+    pub payment_count: u64,
+    /// 账单支付金额
+    pub payment_amount: i64,
+    /// 账单退款笔数
+    pub refund_count: u64,
+    /// 账单退款金额
+    pub refund_amount: i64,
+    /// 与本地一致的账单记录数
+    pub matched_count: u64,
+    pub discrepancies: Vec<Discrepancy>,
+}
+
+impl DailyReconcile {
+    /// 是否对平
+    pub fn is_balanced(&self) -> bool {
+        self.discrepancies.is_empty()
+    }
+}
+
+/// 对账结果，按日期升序
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReconcileReport {
+    pub days: Vec<DailyReconcile>,
+}
+
+impl ReconcileReport {
+    pub fn is_balanced(&self) -> bool {
+        self.days.iter().all(DailyReconcile::is_balanced)
+    }
+
+    /// 所有日期的差异
+    pub fn discrepancies(&self) -> impl Iterator<Item = &Discrepancy> {
+        self.days.iter().flat_map(|day| day.discrepancies.iter())
+    }
+}
+
+/// 比较交易账单记录与本地订单，dates为对账的日期(yyyy-MM-dd)，可用`date_range`生成
+///
+/// 账单中`trade_state`为REFUND的记录按商户退款单号与本地退款比较，其余记录按订单比较；
+/// 对dates中的每一天(包括账单中没有记录的日期)，再用`paid_orders`找出本地已支付但账单中没有支付记录的订单
+pub fn reconcile<'a, D, I, O>(dates: D, rows: I, source: &O) -> ReconcileReport
+where
+    D: IntoIterator,
+    D::Item: AsRef<str>,
+    I: IntoIterator<Item = &'a TradeBillRow>,
+    O: OrderSource + ?Sized,
+{
+    let mut days: BTreeMap<String, DailyReconcile> = BTreeMap::new();
+    let dates: Vec<String> = dates
+        .into_iter()
+        .map(|date| date.as_ref().to_string())
+        .collect();
+    for date in &dates {
+        days.entry(date.clone()).or_insert_with(|| DailyReconcile {
+            date: date.clone(),
+            ..Default::default()
+        });
+    }
+    let mut paid: HashSet<String> = HashSet::new();
+    for row in rows {
+        let date = row.trade_time.get(..10).unwrap_or_default().to_string();
+        let day = days.entry(date.clone()).or_insert_with(|| DailyReconcile {
+            date,
+            ..Default::default()
+        });
+        let local = source.order(row.out_trade_no.as_str());
+        let before = day.discrepancies.len();
+        if row.trade_state == "REFUND" {
+            day.refund_count += 1;
+            day.refund_amount += refund_amount(row);
+            reconcile_refund(row, local.as_ref(), &mut day.discrepancies);
+        } else {
+            day.payment_count += 1;
+            day.payment_amount += payment_amount(row);
+            paid.insert(row.out_trade_no.clone());
+            reconcile_payment(row, local.as_ref(), &mut day.discrepancies);
+        }
+        if day.discrepancies.len() == before {
+            day.matched_count += 1;
+        }
+    }
+    for date in &dates {
+        let Some(day) = days.get_mut(date) else {
+            continue;
+        };
+        for order in source.paid_orders(date.as_str()) {
+            if order.is_paid() && !paid.contains(&order.out_trade_no) {
+                day.discrepancies.push(Discrepancy::MissingWechat {
+                    out_trade_no: order.out_trade_no,
+                    local: order.total,
+                });
+            }
+        }
+    }
+    ReconcileReport {
+        days: days.into_values().collect(),
+    }
+}
+
+/// 生成start到end(均包含，yyyy-MM-dd)的每一天
+pub fn date_range(start: &str, end: &str) -> Result<Vec<String>, PayError> {
+    let parse = |date: &str| {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map_err(|e| PayError::BillError(format!("invalid date {}: {}", date, e)))
+    };
+    let (start, end) = (parse(start)?, parse(end)?);
+    if start > end {
+        return Err(PayError::BillError(format!(
+            "start date {} is after end date {}",
+            start, end
+        )));
+    }
+    Ok(start
+        .iter_days()
+        .take_while(|date| *date <= end)
+        .map(|date| date.format("%Y-%m-%d").to_string())
+        .collect())
+}
+
+/// 支付记录金额，优先使用订单金额，旧版账单无此列时使用应结订单金额与代金券金额之和
+fn payment_amount(row: &TradeBillRow) -> i64 {
+    if row.total_fee != 0 {
+        row.total_fee
+    } else {
+        row.settlement_total_fee + row.coupon_fee
+    }
+}
+
+/// 退款记录金额，优先使用申请退款金额
+fn refund_amount(row: &TradeBillRow) -> i64 {
+    if row.apply_refund_fee != 0 {
+        row.apply_refund_fee
+    } else {
+        row.refund_fee + row.coupon_refund_fee
+    }
+}
+
+fn reconcile_payment(
+    row: &TradeBillRow,
+    local: Option<&LocalOrder>,
+    discrepancies: &mut Vec<Discrepancy>,
+) {
+    let wechat = payment_amount(row);
+    let Some(local) = local else {
+        discrepancies.push(Discrepancy::MissingLocal {
+            out_trade_no: row.out_trade_no.clone(),
+            wechat,
+        });
+        return;
+    };
+    let wechat_paid = row.trade_state == "SUCCESS";
+    if wechat_paid != local.is_paid() {
+        discrepancies.push(Discrepancy::StatusMismatch {
+            out_trade_no: row.out_trade_no.clone(),
+            local: local.trade_state.clone(),
+            wechat: row.trade_state.clone(),
+        });
+    }
+    if local.total != wechat {
+        discrepancies.push(Discrepancy::AmountMismatch {
+            out_trade_no: row.out_trade_no.clone(),
+            local: local.total,
+            wechat,
+        });
+    }
+}
+
+fn reconcile_refund(
+    row: &TradeBillRow,
+    local: Option<&LocalOrder>,
+    discrepancies: &mut Vec<Discrepancy>,
+) {
+    let wechat = refund_amount(row);
+    let local_refund = local.and_then(|order| {
+        order
+            .refunds
+            .iter()
+            .find(|refund| refund.out_refund_no == row.out_refund_no)
+    });
+    // 微信侧退款失败的记录不计入比较
+    if row.refund_status != RefundStatus::Success.to_string() && local_refund.is_none() {
+        return;
+    }
+    if local_refund.map(|refund| refund.refund) != Some(wechat) {
+        discrepancies.push(Discrepancy::RefundMismatch {
+            out_trade_no: row.out_trade_no.clone(),
+            out_refund_no: row.out_refund_no.clone(),
+            local: local_refund.map(|refund| refund.refund),
+            wechat,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bill::tests::TRADE_BILL;
+    use crate::bill::TradeBill;
+    use crate::reconcile::{
+        date_range, reconcile, Discrepancy, LocalOrder, LocalRefund, OrderSource,
+    };
+    use std::collections::HashMap;
+
+    struct Orders(HashMap<String, (String, LocalOrder)>);
+
+    impl OrderSource for Orders {
+        fn order(&self, out_trade_no: &str) -> Option<LocalOrder> {
+            self.0.get(out_trade_no).map(|(_, order)| order.clone())
+        }
+
+        fn paid_orders(&self, date: &str) -> Vec<LocalOrder> {
+            self.0
+                .values()
+                .filter(|(paid_date, _)| paid_date == date)
+                .map(|(_, order)| order.clone())
+                .collect()
+        }
+    }
+
+    fn order(out_trade_no: &str, total: i64, trade_state: &str) -> LocalOrder {
+        LocalOrder {
+            out_trade_no: out_trade_no.to_string(),
+            total,
+            trade_state: trade_state.to_string(),
+            refunds: vec![],
+        }
+    }
+
+    /// 本地订单与其支付日期
+    fn orders(orders: Vec<(&str, LocalOrder)>) -> Orders {
+        Orders(
+            orders
+                .into_iter()
+                .map(|(date, order)| (order.out_trade_no.clone(), (date.to_string(), order)))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_reconcile_balanced() {
+        let bill = TradeBill::parse(TRADE_BILL).unwrap();
+        let mut refunded = order("20240102002", 50, "REFUND");
+        refunded.refunds.push(LocalRefund {
+            out_refund_no: "R20240102002".to_string(),
+            refund: 50,
+        });
+        // 退款的订单在之前的日期支付
+        let source = orders(vec![
+            ("2024-01-02", order("20240102001", 100, "SUCCESS")),
+            ("2024-01-01", refunded),
+        ]);
+        let report = reconcile(["2024-01-02"], &bill.rows, &source);
+        assert!(report.is_balanced(), "{:?}", report);
+        assert_eq!(report.days.len(), 1);
+        let day = &report.days[0];
+        assert_eq!(day.date, "2024-01-02");
+        assert_eq!(day.payment_count, 1);
+        assert_eq!(day.payment_amount, 100);
+        assert_eq!(day.refund_count, 1);
+        assert_eq!(day.refund_amount, 50);
+        assert_eq!(day.matched_count, 2);
+    }
+
+    #[test]
+    fn test_reconcile_discrepancies() {
+        let bill = TradeBill::parse(TRADE_BILL).unwrap();
+        let mut refunded = order("20240102002", 50, "REFUND");
+        refunded.refunds.push(LocalRefund {
+            out_refund_no: "R20240102002".to_string(),
+            refund: 30,
+        });
+        let source = orders(vec![
+            ("2024-01-02", order("20240102001", 200, "NOTPAY")),
+            ("2024-01-01", refunded),
+            ("2024-01-02", order("20240102003", 300, "SUCCESS")),
+        ]);
+        let report = reconcile(["2024-01-02"], &bill.rows, &source);
+        assert!(!report.is_balanced());
+        let discrepancies: Vec<&Discrepancy> = report.discrepancies().collect();
+        assert_eq!(
+            discrepancies,
+            vec![
+                &Discrepancy::StatusMismatch {
+                    out_trade_no: "20240102001".to_string(),
+                    local: "NOTPAY".to_string(),
+                    wechat: "SUCCESS".to_string(),
+                },
+                &Discrepancy::AmountMismatch {
+                    out_trade_no: "20240102001".to_string(),
+                    local: 200,
+                    wechat: 100,
+                },
+                &Discrepancy::RefundMismatch {
+                    out_trade_no: "20240102002".to_string(),
+                    out_refund_no: "R20240102002".to_string(),
+                    local: Some(30),
+                    wechat: 50,
+                },
+                &Discrepancy::MissingWechat {
+                    out_trade_no: "20240102003".to_string(),
+                    local: 300,
+                },
+            ]
+        );
+        assert_eq!(report.days[0].matched_count, 0);
+
+        let report = reconcile(["2024-01-02"], &bill.rows, &orders(vec![]));
+        assert!(matches!(
+            report.discrepancies().next(),
+            Some(Discrepancy::MissingLocal { wechat: 100, .. })
+        ));
+    }
+
+    #[test]
+    fn test_reconcile_date_range() {
+        let bill = TradeBill::parse(TRADE_BILL).unwrap();
+        let mut refunded = order("20240102002", 50, "REFUND");
+        refunded.refunds.push(LocalRefund {
+            out_refund_no: "R20240102002".to_string(),
+            refund: 50,
+        });
+        // 账单中没有记录的日期也要检查微信侧缺失的订单
+        let source = orders(vec![
+            ("2024-01-02", order("20240102001", 100, "SUCCESS")),
+            ("2024-01-01", refunded),
+            ("2024-01-03", order("20240103001", 300, "SUCCESS")),
+        ]);
+        let dates = date_range("2024-01-01", "2024-01-03").unwrap();
+        assert_eq!(dates, ["2024-01-01", "2024-01-02", "2024-01-03"]);
+        let report = reconcile(&dates, &bill.rows, &source);
+        let days: Vec<&str> = report.days.iter().map(|day| day.date.as_str()).collect();
+        assert_eq!(days, dates);
+        // 只传入了2024-01-02的账单，2024-01-01支付的订单在微信侧缺失
+        assert_eq!(
+            report.days[0].discrepancies,
+            [Discrepancy::MissingWechat {
+                out_trade_no: "20240102002".to_string(),
+                local: 50,
+            }]
+        );
+        assert!(report.days[1].is_balanced());
+        assert_eq!(
+            report.days[2].discrepancies,
+            [Discrepancy::MissingWechat {
+                out_trade_no: "20240103001".to_string(),
+                local: 300,
+            }]
+        );
+
+        assert!(date_range("2024-01-03", "2024-01-01").is_err());
+        assert!(date_range("2024/01/01", "2024-01-03").is_err());
+    }
+}
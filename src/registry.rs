@@ -0,0 +1,439 @@
+//! 多商户管理，按商户号保存`WechatPay`，支持热更新商户配置与按回调内容路由到对应商户
+//!
+//! 替换商户配置时正在进行的请求继续使用旧配置，新请求使用新配置；每个商户有独立的平台证书缓存，热更新时保留
+use crate::builder::WechatPayBuilder;
+use crate::certificate::PlatformCertificateManager;
+use crate::debug;
+use crate::error::{ConfigError, PayError};
+use crate::model::WechatPayNotification;
+use crate::pay::{PayNotifyTrait, WechatPay, WECHATPAY_SERIAL};
+use http::HeaderMap;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+#[cfg(feature = "async")]
+use maybe_async::maybe_async as maybe_async_attr;
+#[cfg(not(feature = "async"))]
+use maybe_async::must_be_sync as maybe_async_attr;
+
+#[derive(Debug, Default)]
+struct Merchants {
+    /// 商户号 -> 商户
+    by_mch_id: HashMap<String, Arc<WechatPay>>,
+    /// 配置文件 -> 商户号，用于热更新时移除已删除的配置文件对应的商户
+    sources: HashMap<PathBuf, String>,
+}
+
+/// 多商户管理
+///
+/// ```no_run
+/// use wechat_pay_rust_sdk::registry::MerchantRegistry;
+///
+/// let registry = MerchantRegistry::new().with_platform_certificates(true);
+/// //每个商户一个TOML/JSON配置文件，配置变更时再次调用reload_dir
+/// for (path, error) in registry.reload_dir("merchants").unwrap() {
+///     eprintln!("load {} error: {}", path.display(), error);
+/// }
+/// let wechat_pay = registry.get("1230000109").unwrap();
+/// ```
+#[derive(Debug, Default)]
+pub struct MerchantRegistry {
+    merchants: RwLock<Merchants>,
+    platform_certificates: bool,
+}
+
+impl MerchantRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 为未配置微信支付公钥的商户创建独立的平台证书管理器，自动下载并缓存平台证书
+    pub fn with_platform_certificates(mut self, enabled: bool) -> Self {
+        self.platform_certificates = enabled;
+        self
+    }
+
+    /// 添加或替换商户，返回被替换的旧配置
+    ///
+    /// 新配置未设置平台证书管理器时沿用旧配置的证书缓存
+    pub fn insert(&self, wechat_pay: WechatPay) -> Option<Arc<WechatPay>> {
+        let mut merchants = self.merchants.write().unwrap();
+        self.insert_locked(&mut merchants, wechat_pay)
+    }
+
+    fn insert_locked(
+        &self,
+        merchants: &mut Merchants,
+        mut wechat_pay: WechatPay,
+    ) -> Option<Arc<WechatPay>> {
+        let previous = merchants.by_mch_id.get(&wechat_pay.mch_id);
+        if wechat_pay.certificate_manager.is_none() && wechat_pay.platform_public_key.is_none() {
+            wechat_pay.certificate_manager = previous
+                .and_then(|previous| previous.certificate_manager.clone())
+                .or_else(|| {
+                    self.platform_certificates
+                        .then(|| Arc::new(PlatformCertificateManager::new()))
+                });
+        }
+        merchants
+            .by_mch_id
+            .insert(wechat_pay.mch_id.clone(), Arc::new(wechat_pay))
+    }
+
+    pub fn remove(&self, mch_id: &str) -> Option<Arc<WechatPay>> {
+        let mut merchants = self.merchants.write().unwrap();
+        merchants.sources.retain(|_, source| source != mch_id);
+        merchants.by_mch_id.remove(mch_id)
+    }
+
+    /// 按商户号查找
+    pub fn get(&self, mch_id: &str) -> Option<Arc<WechatPay>> {
+        self.merchants
+            .read()
+            .unwrap()
+            .by_mch_id
+            .get(mch_id)
+            .cloned()
+    }
+
+    /// 按appid查找，同一个appid可以绑定多个商户
+    pub fn get_by_appid(&self, appid: &str) -> Vec<Arc<WechatPay>> {
+        self.merchants
+            .read()
+            .unwrap()
+            .by_mch_id
+            .values()
+            .filter(|wechat_pay| wechat_pay.appid == appid)
+            .cloned()
+            .collect()
+    }
+
+    pub fn mch_ids(&self) -> Vec<String> {
+        self.merchants
+            .read()
+            .unwrap()
+            .by_mch_id
+            .keys()
+            .cloned()
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.merchants.read().unwrap().by_mch_id.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 加载目录下所有`.toml`与`.json`商户配置，每个文件一个商户，文件中的私钥与证书路径以该目录为准
+    ///
+    /// 加载失败的文件保留旧配置并返回错误，上次从该目录加载、本次已删除的配置文件对应的商户被移除
+    pub fn reload_dir(&self, dir: impl AsRef<Path>) -> Result<Vec<(PathBuf, PayError)>, PayError> {
+        let dir = dir.as_ref();
+        let mut paths = std::fs::read_dir(dir)
+            .map_err(|source| ConfigError::ReadFile {
+                path: dir.display().to_string(),
+                source,
+            })?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                matches!(
+                    path.extension().and_then(|ext| ext.to_str()),
+                    Some("toml") | Some("json")
+                )
+            })
+            .collect::<Vec<_>>();
+        paths.sort();
+
+        // 在锁外读取文件与解析私钥
+        let loaded = paths
+            .into_iter()
+            .map(|path| {
+                let result = WechatPayBuilder::new().load_file(&path).build();
+                (path, result)
+            })
+            .collect::<Vec<_>>();
+
+        let mut merchants = self.merchants.write().unwrap();
+        let mut sources = HashMap::new();
+        let mut errors = Vec::new();
+        for (path, result) in loaded {
+            match result {
+                Ok(wechat_pay) => {
+                    let mch_id = &wechat_pay.mch_id;
+                    debug!("reload merchant {} from {:?}", mch_id, path);
+                    sources.insert(path, mch_id.clone());
+                    self.insert_locked(&mut merchants, wechat_pay);
+                }
+                Err(error) => {
+                    if let Some(mch_id) = merchants.sources.get(&path) {
+                        sources.insert(path.clone(), mch_id.clone());
+                    }
+                    errors.push((path, error));
+                }
+            }
+        }
+        let removed = merchants
+            .sources
+            .iter()
+            .filter(|(path, mch_id)| {
+                path.starts_with(dir) && !sources.values().any(|id| id == *mch_id)
+            })
+            .map(|(path, mch_id)| (path.clone(), mch_id.clone()))
+            .collect::<Vec<_>>();
+        for (path, mch_id) in removed {
+            debug!("remove merchant {} of {:?}", mch_id, path);
+            merchants.sources.remove(&path);
+            merchants.by_mch_id.remove(&mch_id);
+        }
+        merchants.sources.extend(sources);
+        Ok(errors)
+    }
+
+    /// 找到回调所属的商户
+    ///
+    /// 依次用各商户的平台公钥验签(平台证书缓存为空或序列号未知时先下载平台证书)，再用其APIv3密钥解密resource；
+    /// 返回解密成功的商户，有多个商户能解密时优先返回resource中`mchid`(服务商为`sp_mchid`)对应的商户
+    #[maybe_async_attr]
+    pub async fn route_notification(
+        &self,
+        headers: &HeaderMap,
+        body: impl AsRef<str>,
+    ) -> Result<Arc<WechatPay>, PayError> {
+        let (wechat_pay, _) = self.route(headers, body.as_ref()).await?;
+        Ok(wechat_pay)
+    }
+
+    /// 路由到对应商户并完成验签、时间戳校验与解密
+    #[maybe_async_attr]
+    pub async fn parse_notification(
+        &self,
+        headers: &HeaderMap,
+        body: impl AsRef<str>,
+    ) -> Result<(Arc<WechatPay>, WechatPayNotification), PayError> {
+        self.route(headers, body.as_ref()).await
+    }
+
+    #[maybe_async_attr]
+    async fn route(
+        &self,
+        headers: &HeaderMap,
+        body: &str,
+    ) -> Result<(Arc<WechatPay>, WechatPayNotification), PayError> {
+        let serial_no = headers
+            .get(WECHATPAY_SERIAL)
+            .and_then(|value| value.to_str().ok())
+            .ok_or_else(|| PayError::VerifyError(format!("missing header {}", WECHATPAY_SERIAL)))?;
+        let merchants = self
+            .merchants
+            .read()
+            .unwrap()
+            .by_mch_id
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut decrypted = None;
+        let mut error = None;
+        for wechat_pay in merchants {
+            match wechat_pay.fetch_platform_pub_key(serial_no).await {
+                Ok(Some(_)) => {}
+                Ok(None) => continue,
+                Err(e) => {
+                    error.get_or_insert(e);
+                    continue;
+                }
+            }
+            let result = wechat_pay
+                .verify_notification(headers, body)
+                .and_then(|notify| {
+                    let plaintext = wechat_pay.decrypt_notify_resource(&notify)?;
+                    Ok((notify, plaintext))
+                });
+            let (notify, plaintext) = match result {
+                Ok(result) => result,
+                Err(e) => {
+                    error = Some(e);
+                    continue;
+                }
+            };
+            let matched = resource_mch_id(plaintext.as_slice())
+                .is_none_or(|mch_id| mch_id == wechat_pay.mch_id);
+            let notification = WechatPayNotification::from_plaintext(notify, plaintext.as_slice())?;
+            if matched {
+                return Ok((wechat_pay, notification));
+            }
+            decrypted.get_or_insert((wechat_pay, notification));
+        }
+        decrypted.ok_or_else(|| {
+            error.unwrap_or_else(|| {
+                PayError::VerifyError(format!("platform pub key {} not found", serial_no))
+            })
+        })
+    }
+}
+
+/// resource中的商户号，服务商模式为`sp_mchid`
+fn resource_mch_id(plaintext: &[u8]) -> Option<String> {
+    let resource: Value = serde_json::from_slice(plaintext).ok()?;
+    ["sp_mchid", "mchid"]
+        .iter()
+        .find_map(|key| resource.get(key).and_then(Value::as_str))
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::async_impl::pay::tests::{stub_server, stub_wechat_pay};
+    use crate::certificate::tests::{certificates_response, PLATFORM_KEY, PLATFORM_SERIAL};
+    use crate::model::WechatPayNotification;
+    use crate::pay::tests::{encrypt_resource, notify_headers};
+    use crate::pay::{WechatPay, WECHATPAY_SERIAL};
+    use crate::registry::MerchantRegistry;
+    use rsa::pkcs8::{DecodePrivateKey, EncodePublicKey, LineEnding};
+    use rsa::rand_core::OsRng;
+    use rsa::{RsaPrivateKey, RsaPublicKey};
+    use std::sync::Arc;
+
+    const V3_KEY_A: &str = "0123456789abcdef0123456789abcdef";
+    const V3_KEY_B: &str = "fedcba9876543210fedcba9876543210";
+
+    #[allow(deprecated)]
+    fn merchant(mch_id: &str, v3_key: &str, pub_key: &str) -> WechatPay {
+        WechatPay::new("wxd678efh567hg6787", mch_id, "", "", v3_key, "")
+            .with_platform_pub_key("PLATFORM_SERIAL", pub_key)
+    }
+
+    fn notify_body(v3_key: &str, mch_id: &str) -> String {
+        let plaintext = format!(
+            r#"{{"mchid":"{}","appid":"wxd678efh567hg6787","out_trade_no":"1217752501201407033233368018","transaction_id":"1217752501201407033233368018","trade_type":"JSAPI","trade_state":"SUCCESS","trade_state_desc":"支付成功","bank_type":"CMC","success_time":"2018-06-08T10:34:56+08:00","payer":{{"openid":"oUpF8uMuAJO_M2pxb1Q9zNjWeS6o"}},"amount":{{"total":100}}}}"#,
+            mch_id
+        );
+        let ciphertext = encrypt_resource(v3_key, "uaGeNnBYNjl7", "transaction", &plaintext);
+        format!(
+            r#"{{"id":"EV-2018022511223320873","create_time":"2018-06-08T10:34:56+08:00","resource_type":"encrypt-resource","event_type":"TRANSACTION.SUCCESS","summary":"支付成功","resource":{{"original_type":"transaction","algorithm":"AEAD_AES_256_GCM","ciphertext":"{}","associated_data":"transaction","nonce":"uaGeNnBYNjl7"}}}}"#,
+            ciphertext
+        )
+    }
+
+    /// 多个商户共用平台公钥时，按APIv3密钥与resource中的mchid路由
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_route_notification() {
+        let platform_key = RsaPrivateKey::new(&mut OsRng, 1024).unwrap();
+        let pub_key = RsaPublicKey::from(&platform_key)
+            .to_public_key_pem(LineEnding::LF)
+            .unwrap();
+        let registry = MerchantRegistry::new();
+        registry.insert(merchant("1900000100", V3_KEY_A, &pub_key));
+        registry.insert(merchant("1900000200", V3_KEY_B, &pub_key));
+        assert_eq!(registry.len(), 2);
+        assert_eq!(registry.get_by_appid("wxd678efh567hg6787").len(), 2);
+
+        let now = chrono::Local::now().timestamp();
+        for (v3_key, mch_id) in [(V3_KEY_A, "1900000100"), (V3_KEY_B, "1900000200")] {
+            let body = notify_body(v3_key, mch_id);
+            let headers = notify_headers(&platform_key, now, &body);
+            let (wechat_pay, notification) =
+                registry.parse_notification(&headers, &body).await.unwrap();
+            assert_eq!(wechat_pay.mch_id, mch_id);
+            match notification {
+                WechatPayNotification::Transaction { data, .. } => assert_eq!(data.mchid, mch_id),
+                other => panic!("unexpected notification: {:?}", other),
+            }
+        }
+
+        // 返回实际解密成功的商户，而不是resource中mchid对应的商户
+        let body = notify_body(V3_KEY_B, "1900000100");
+        let headers = notify_headers(&platform_key, now, &body);
+        let wechat_pay = registry.route_notification(&headers, &body).await.unwrap();
+        assert_eq!(wechat_pay.mch_id, "1900000200");
+
+        // 多个商户共用APIv3密钥时优先返回mchid对应的商户
+        registry.insert(merchant("1900000300", V3_KEY_A, &pub_key));
+        for mch_id in ["1900000100", "1900000300"] {
+            let body = notify_body(V3_KEY_A, mch_id);
+            let headers = notify_headers(&platform_key, now, &body);
+            let wechat_pay = registry.route_notification(&headers, &body).await.unwrap();
+            assert_eq!(wechat_pay.mch_id, mch_id);
+        }
+
+        // 未注册的APIv3密钥无法解密
+        let body = notify_body("00000000000000000000000000000000", "1900000400");
+        let headers = notify_headers(&platform_key, now, &body);
+        let result = registry.route_notification(&headers, &body).await;
+        assert!(result.is_err());
+
+        // 伪造的签名
+        let forged_key = RsaPrivateKey::new(&mut OsRng, 1024).unwrap();
+        let body = notify_body(V3_KEY_A, "1900000100");
+        let headers = notify_headers(&forged_key, now, &body);
+        let result = registry.route_notification(&headers, &body).await;
+        assert!(result.is_err());
+    }
+
+    /// 平台证书缓存为空的商户，路由前先下载平台证书
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_route_notification_download_certificates() {
+        let platform_key = RsaPrivateKey::from_pkcs8_pem(PLATFORM_KEY).unwrap();
+        let base_url = stub_server(vec![certificates_response(&platform_key, PLATFORM_SERIAL)]);
+        let other_key = RsaPrivateKey::new(&mut OsRng, 1024).unwrap();
+        let wechat_pay = stub_wechat_pay(base_url.as_str(), &other_key);
+        let registry = MerchantRegistry::new().with_platform_certificates(true);
+        registry.insert(wechat_pay);
+
+        let body = notify_body(V3_KEY_A, "1900000100");
+        let mut headers = notify_headers(&platform_key, chrono::Local::now().timestamp(), &body);
+        headers.insert(WECHATPAY_SERIAL, PLATFORM_SERIAL.parse().unwrap());
+        let (wechat_pay, _) = registry.parse_notification(&headers, &body).await.unwrap();
+        assert_eq!(wechat_pay.mch_id, "1900000100");
+        let manager = wechat_pay.certificate_manager.as_ref().unwrap();
+        assert!(manager.public_key_for(PLATFORM_SERIAL).is_some());
+    }
+
+    /// 热更新替换商户配置，保留平台证书缓存，删除配置文件后移除商户
+    #[test]
+    fn test_reload_dir() {
+        let dir = std::env::temp_dir().join(format!("wechat-pay-registry-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let fixtures = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+        let config = |mch_id: &str, notify_url: &str| {
+            format!(
+                "appid = \"wxd678efh567hg6787\"\nmch_id = \"{}\"\nserial_no = \"MERCHANT_SERIAL\"\nprivate_key_path = \"{}/platform_key.pem\"\nv3_key = \"{}\"\nnotify_url = \"{}\"\n",
+                mch_id, fixtures, V3_KEY_A, notify_url
+            )
+        };
+        std::fs::write(dir.join("a.toml"), config("1900000100", "https://a.com/v1")).unwrap();
+        std::fs::write(dir.join("b.toml"), config("1900000200", "https://b.com")).unwrap();
+        std::fs::write(dir.join("readme.txt"), "ignored").unwrap();
+
+        let registry = MerchantRegistry::new().with_platform_certificates(true);
+        assert!(registry.reload_dir(&dir).unwrap().is_empty());
+        let mut mch_ids = registry.mch_ids();
+        mch_ids.sort();
+        assert_eq!(mch_ids, ["1900000100", "1900000200"]);
+        let old = registry.get("1900000100").unwrap();
+        let manager = old.certificate_manager.clone().unwrap();
+
+        std::fs::write(dir.join("a.toml"), config("1900000100", "https://a.com/v2")).unwrap();
+        std::fs::write(dir.join("b.toml"), "appid = ").unwrap();
+        let errors = registry.reload_dir(&dir).unwrap();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].0.ends_with("b.toml"));
+        let new = registry.get("1900000100").unwrap();
+        assert_eq!(old.notify_url, "https://a.com/v1");
+        assert_eq!(new.notify_url, "https://a.com/v2");
+        assert!(Arc::ptr_eq(
+            new.certificate_manager.as_ref().unwrap(),
+            &manager
+        ));
+        assert!(registry.get("1900000200").is_some());
+
+        std::fs::remove_file(dir.join("b.toml")).unwrap();
+        assert!(registry.reload_dir(&dir).unwrap().is_empty());
+        assert!(registry.get("1900000200").is_none());
+        assert_eq!(registry.len(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
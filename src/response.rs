@@ -1,11 +1,18 @@
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
 
 pub trait ResponseTrait: DeserializeOwned {}
 
 #[derive(Debug, Deserialize)]
 pub struct NativeResponse {
+    #[deprecated(
+        note = "非2xx应答返回PayError::WechatError，该字段不再有值，请使用WechatApiError::code"
+    )]
     pub code: Option<String>,
+    #[deprecated(
+        note = "非2xx应答返回PayError::WechatError，该字段不再有值，请使用WechatApiError::message"
+    )]
     pub message: Option<String>,
     ///【支付跳转链接】 h5_url为拉起微信支付收银台的中间页面，可通过访问该URL来拉起微信客户端，完成支付，h5_url的有效期为5分钟。
     pub code_url: Option<String>,
@@ -15,7 +22,13 @@ impl ResponseTrait for NativeResponse {}
 
 #[derive(Debug, Deserialize)]
 pub struct JsapiResponse {
+    #[deprecated(
+        note = "非2xx应答返回PayError::WechatError，该字段不再有值，请使用WechatApiError::code"
+    )]
     pub code: Option<String>,
+    #[deprecated(
+        note = "非2xx应答返回PayError::WechatError，该字段不再有值，请使用WechatApiError::message"
+    )]
     pub message: Option<String>,
     ///【预支付交易会话标识】 预支付交易会话标识。用于后续接口调用中使用，该值有效期为2小时
     pub prepay_id: Option<String>,
@@ -37,7 +50,13 @@ impl ResponseTrait for JsapiResponse {}
 
 #[derive(Debug, Deserialize)]
 pub struct AppResponse {
+    #[deprecated(
+        note = "非2xx应答返回PayError::WechatError，该字段不再有值，请使用WechatApiError::code"
+    )]
     pub code: Option<String>,
+    #[deprecated(
+        note = "非2xx应答返回PayError::WechatError，该字段不再有值，请使用WechatApiError::message"
+    )]
     pub message: Option<String>,
     ///【预支付交易会话标识】 预支付交易会话标识。用于后续接口调用中使用，该值有效期为2小时
     pub prepay_id: Option<String>,
@@ -49,7 +68,13 @@ impl ResponseTrait for AppResponse {}
 
 #[derive(Debug, Deserialize)]
 pub struct MicroResponse {
+    #[deprecated(
+        note = "非2xx应答返回PayError::WechatError，该字段不再有值，请使用WechatApiError::code"
+    )]
     pub code: Option<String>,
+    #[deprecated(
+        note = "非2xx应答返回PayError::WechatError，该字段不再有值，请使用WechatApiError::message"
+    )]
     pub message: Option<String>,
     ///【预支付交易会话标识】 预支付交易会话标识。用于后续接口调用中使用，该值有效期为2小时
     pub prepay_id: Option<String>,
@@ -61,7 +86,13 @@ impl ResponseTrait for MicroResponse {}
 
 #[derive(Debug, Deserialize)]
 pub struct H5Response {
+    #[deprecated(
+        note = "非2xx应答返回PayError::WechatError，该字段不再有值，请使用WechatApiError::code"
+    )]
     pub code: Option<String>,
+    #[deprecated(
+        note = "非2xx应答返回PayError::WechatError，该字段不再有值，请使用WechatApiError::message"
+    )]
     pub message: Option<String>,
     ///【二维码链接】 此URL用于生成支付二维码，然后提供给用户扫码支付。
     /// 注意：code_url并非固定值，使用时按照URL格式转成二维码即可。
@@ -136,6 +167,21 @@ pub struct ErrorResponse {
     pub code: Option<String>,
     /// 【错误信息】 错误信息
     pub message: Option<String>,
+    /// 【错误详情】 参数错误时返回出错的字段
+    pub detail: Option<ErrorDetail>,
+}
+
+/// 【错误详情】
+#[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct ErrorDetail {
+    /// 【出错字段】
+    pub field: Option<String>,
+    /// 【出错字段的值】
+    pub value: Option<String>,
+    /// 【具体错误原因】
+    pub issue: Option<String>,
+    /// 【出错字段的位置】 如body、query、path
+    pub location: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -154,7 +200,7 @@ pub struct RefundsResponse {
     /// BALANCE: 退回到余额
     /// OTHER_BALANCE: 原账户异常退到其他余额账户
     /// OTHER_BANKCARD: 原银行卡异常退到其他银行卡(发起异常退款成功后返回)
-    pub channel: String,
+    pub channel: RefundChannel,
     /// 【退款入账账户】 取当前退款单的退款入账方，有以下几种情况：
     /// 1）退回银行卡：{银行名称}{卡类型}{卡尾号}
     /// 2）退回支付用户零钱:支付用户零钱
@@ -182,7 +228,7 @@ pub struct RefundsResponse {
     /// PROCESSING: 退款处理中
     /// ABNORMAL: 退款异常，退款到银行发现用户的卡作废或者冻结了，导致原路退款银行卡失败，可前往商户平台-交易中心，手动处理此笔退款，可参考： 退款异常的处理，或者通过发起异常退款接口进行处理。
     /// 注：状态流转说明请参考状态流转图
-    pub status: String,
+    pub status: RefundStatus,
     /// 【资金账户】 退款所使用资金对应的资金账户类型
     /// UNSETTLED: 未结算资金
     /// AVAILABLE: 可用余额
@@ -190,7 +236,7 @@ pub struct RefundsResponse {
     /// OPERATION: 运营账户
     /// BASIC: 基本账户（含可用余额和不可用余额）
     /// ECNY_BASIC: 数字人民币基本账户
-    pub funds_account: String,
+    pub funds_account: FundsAccount,
     /// 【金额信息】订单退款金额信息
     pub amount: RefundsAmountResponse,
     /// 【优惠退款详情】 订单各个代金券的退款详情，订单使用了代金券且代金券发生退款时返回。
@@ -199,6 +245,117 @@ pub struct RefundsResponse {
 
 impl ResponseTrait for RefundsResponse {}
 
+/// 【退款状态】 未识别的状态保留原始字符串
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(from = "String")]
+pub enum RefundStatus {
+    Success,
+    Closed,
+    Processing,
+    Abnormal,
+    Unknown(String),
+}
+
+impl From<String> for RefundStatus {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "SUCCESS" => RefundStatus::Success,
+            "CLOSED" => RefundStatus::Closed,
+            "PROCESSING" => RefundStatus::Processing,
+            "ABNORMAL" => RefundStatus::Abnormal,
+            _ => RefundStatus::Unknown(value),
+        }
+    }
+}
+
+impl Display for RefundStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RefundStatus::Success => write!(f, "SUCCESS"),
+            RefundStatus::Closed => write!(f, "CLOSED"),
+            RefundStatus::Processing => write!(f, "PROCESSING"),
+            RefundStatus::Abnormal => write!(f, "ABNORMAL"),
+            RefundStatus::Unknown(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+/// 【退款渠道】 未识别的渠道保留原始字符串
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(from = "String")]
+pub enum RefundChannel {
+    Original,
+    Balance,
+    OtherBalance,
+    OtherBankcard,
+    Unknown(String),
+}
+
+impl From<String> for RefundChannel {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "ORIGINAL" => RefundChannel::Original,
+            "BALANCE" => RefundChannel::Balance,
+            "OTHER_BALANCE" => RefundChannel::OtherBalance,
+            "OTHER_BANKCARD" => RefundChannel::OtherBankcard,
+            _ => RefundChannel::Unknown(value),
+        }
+    }
+}
+
+impl Display for RefundChannel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RefundChannel::Original => write!(f, "ORIGINAL"),
+            RefundChannel::Balance => write!(f, "BALANCE"),
+            RefundChannel::OtherBalance => write!(f, "OTHER_BALANCE"),
+            RefundChannel::OtherBankcard => write!(f, "OTHER_BANKCARD"),
+            RefundChannel::Unknown(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+/// 【资金账户】 未识别的账户类型保留原始字符串
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(from = "String")]
+pub enum FundsAccount {
+    Unsettled,
+    Available,
+    Unavailable,
+    Operation,
+    Basic,
+    EcnyBasic,
+    Unknown(String),
+}
+
+impl From<String> for FundsAccount {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "UNSETTLED" => FundsAccount::Unsettled,
+            "AVAILABLE" => FundsAccount::Available,
+            "UNAVAILABLE" => FundsAccount::Unavailable,
+            "OPERATION" => FundsAccount::Operation,
+            "BASIC" => FundsAccount::Basic,
+            "ECNY_BASIC" => FundsAccount::EcnyBasic,
+            _ => FundsAccount::Unknown(value),
+        }
+    }
+}
+
+impl Display for FundsAccount {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FundsAccount::Unsettled => write!(f, "UNSETTLED"),
+            FundsAccount::Available => write!(f, "AVAILABLE"),
+            FundsAccount::Unavailable => write!(f, "UNAVAILABLE"),
+            FundsAccount::Operation => write!(f, "OPERATION"),
+            FundsAccount::Basic => write!(f, "BASIC"),
+            FundsAccount::EcnyBasic => write!(f, "ECNY_BASIC"),
+            FundsAccount::Unknown(value) => write!(f, "{}", value),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct RefundsAmountResponse {
     /// 【订单金额】 订单总金额，单位为分
@@ -271,3 +428,152 @@ pub struct RefundsGoodsDetailResponse {
     /// 【商品退货数量】 申请退款的商品退货数量。
     pub refund_quantity: i32,
 }
+
+#[derive(Debug, Deserialize)]
+pub struct TransactionResponse {
+    /// 【应用ID】 商户下单时传入的appid。
+    pub appid: String,
+    /// 【商户号】 商户下单时传入的商户号。
+    pub mchid: String,
+    /// 【商户订单号】 商户下单时传入的商户系统内部订单号。
+    pub out_trade_no: String,
+    /// 【微信支付订单号】 微信支付侧订单的唯一标识，订单支付成功后返回。
+    pub transaction_id: Option<String>,
+    /// 【交易类型】 返回当前订单的交易类型，枚举值：
+    /// JSAPI：公众号支付、小程序支付
+    /// NATIVE：Native支付
+    /// APP：APP支付
+    /// MICROPAY：付款码支付
+    /// MWEB：H5支付
+    /// FACEPAY：刷脸支付
+    pub trade_type: Option<String>,
+    /// 【交易状态】 交易状态，枚举值：
+    /// SUCCESS：支付成功
+    /// REFUND：转入退款
+    /// NOTPAY：未支付
+    /// CLOSED：已关闭
+    /// REVOKED：已撤销（仅付款码支付会返回）
+    /// USERPAYING：用户支付中（仅付款码支付会返回）
+    /// PAYERROR：支付失败（仅付款码支付会返回）
+    pub trade_state: String,
+    /// 【交易状态描述】 对交易状态的详细说明。
+    pub trade_state_desc: String,
+    /// 【银行类型】 用户支付方式说明，订单支付成功后返回，格式为银行简码_具体类型(DEBIT借记卡/CREDIT信用卡/ECNY数字人民币)，例如ICBC_DEBIT代表工商银行借记卡，非银行卡支付类型(例如余额/零钱通等)统一为OTHERS。
+    pub bank_type: Option<String>,
+    /// 【商户数据包】 商户下单时传入的自定义数据包，用户不可见，长度不超过128字符，若下单传入该参数，则订单支付成功后此接口和支付成功回调通知会原样返回。
+    pub attach: Option<String>,
+    /// 【支付完成时间】 订单支付成功后返回，遵循rfc3339标准格式，例如：2015-05-20T13:29:35+08:00。
+    pub success_time: Option<String>,
+    /// 【支付者信息】 订单的支付者信息，订单支付成功后返回。
+    pub payer: Option<TransactionPayerResponse>,
+    /// 【订单金额】 订单金额信息。
+    pub amount: Option<TransactionAmountResponse>,
+    /// 【场景信息】 下单时传入的支付场景描述，若下单传入该参数，则原样返回；若下单未传该参数，则不会返回。
+    pub scene_info: Option<TransactionSceneInfoResponse>,
+    /// 【优惠功能】 代金券信息，当订单支付时，有使用代金券时，该字段将返回所使用的代金券信息。
+    pub promotion_detail: Option<Vec<TransactionPromotionDetailResponse>>,
+}
+
+impl ResponseTrait for TransactionResponse {}
+
+#[derive(Debug, Deserialize)]
+pub struct TransactionPayerResponse {
+    /// 【用户标识】 用户在商户下单的appid下唯一标识。
+    pub openid: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TransactionAmountResponse {
+    /// 【总金额】 订单总金额，单位为分，整型。
+    pub total: Option<i32>,
+    /// 【用户支付金额】 用户实际支付金额，整型，单位为分，用户支付金额=总金额-代金券金额。
+    pub payer_total: Option<i32>,
+    /// 【货币类型】 固定返回：CNY，代表人民币。
+    pub currency: Option<String>,
+    /// 【用户支付币种】 订单支付成功后固定返回：CNY，代表人民币。
+    pub payer_currency: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TransactionSceneInfoResponse {
+    /// 【商户端设备号】 商户下单时传入的商户端设备号（门店号或收银设备ID）。
+    pub device_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TransactionPromotionDetailResponse {
+    /// 【券ID】 代金券id，微信为创建的代金券分配的唯一标识。
+    pub coupon_id: String,
+    /// 【优惠名称】 代金券名称，创建代金券时商户填写的名称。
+    pub name: Option<String>,
+    /// 【优惠范围】 优惠活动中代金券的适用范围，分为两种类型：
+    /// GLOBAL：全场代金券-以订单整体可优惠的金额为优惠门槛的代金券；
+    /// SINGLE：单品优惠-以订单中具体某个单品的总金额为优惠门槛的代金券
+    pub scope: Option<String>,
+    /// 【优惠类型】 代金券资金类型，优惠活动中代金券的结算资金类型，分为两种类型：
+    /// CASH：预充值-带有结算资金的代金券，会随订单结算给订单收款商户；
+    /// NOCASH：免充值-不带有结算资金的代金券，无资金结算给订单收款商户。
+    pub r#type: Option<String>,
+    /// 【优惠券面额】 代金券优惠的金额，单位为分。
+    pub amount: i32,
+    /// 【活动ID】 单张代金券所对应的批次号。
+    pub stock_id: Option<String>,
+    /// 【微信出资】 代金券有三种出资类型：微信出资、商户出资和其他出资。本参数将返回选择“微信出资类型”时的金额，单位为分。
+    pub wechatpay_contribute: Option<i32>,
+    /// 【商户出资】 本参数将返回选择“商户出资类型”时的金额，单位为分。
+    pub merchant_contribute: Option<i32>,
+    /// 【其他出资】 本参数将返回选择“其他出资类型”时的金额，单位为分。
+    pub other_contribute: Option<i32>,
+    /// 【优惠币种】 固定返回：CNY，代表人民币。
+    pub currency: Option<String>,
+    /// 【单品列表】 单品列表信息。
+    pub goods_detail: Option<Vec<TransactionGoodsDetailResponse>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TransactionGoodsDetailResponse {
+    /// 【商品编码】 商户下单时传入的商品编码。
+    pub goods_id: String,
+    /// 【商品数量】 商户下单时传入的商品数量。
+    pub quantity: i32,
+    /// 【商品单价】 商户下单时传入的商品单价，单位为分。
+    pub unit_price: i32,
+    /// 【商品优惠金额】 商品优惠金额，单位为分。
+    pub discount_amount: i32,
+    /// 【商品备注】 商品备注信息。
+    pub goods_remark: Option<String>,
+}
+
+/// 申请账单应答
+#[derive(Debug, Clone, Deserialize)]
+pub struct BillResponse {
+    /// 【哈希类型】 原始账单（gzip需要解压缩）的摘要值类型，对账单文件的哈希类型，目前只有SHA1
+    pub hash_type: String,
+    /// 【哈希值】 原始账单（gzip需要解压缩）的摘要值，用于校验文件的完整性。
+    pub hash_value: String,
+    /// 【账单下载地址】 供下一步请求账单文件的下载地址，该地址30s内有效。
+    pub download_url: String,
+}
+
+impl ResponseTrait for BillResponse {}
+
+#[cfg(test)]
+mod tests {
+    use crate::response::{
+        FundsAccount, RefundChannel, RefundStatus, RefundsResponse, WeChatResponse,
+    };
+
+    #[test]
+    fn test_refunds_response_enums() {
+        let body = r#"{"refund_id":"50000000382019052709732678859","out_refund_no":"1217752501201407033233368018","transaction_id":"1217752501201407033233368018","out_trade_no":"1217752501201407033233368018","channel":"ORIGINAL","user_received_account":"招商银行信用卡0403","success_time":"2020-12-01T16:18:12+08:00","create_time":"2020-12-01T16:18:12+08:00","status":"ABNORMAL","funds_account":"NEW_ACCOUNT","amount":{"total":100,"refund":100,"payer_total":90,"payer_refund":90,"settlement_refund":100,"settlement_total":100,"discount_refund":10,"currency":"CNY"}}"#;
+        let response: WeChatResponse<RefundsResponse> = serde_json::from_str(body).unwrap();
+        let refund = response.ok().unwrap();
+        assert_eq!(refund.status, RefundStatus::Abnormal);
+        assert_eq!(refund.channel, RefundChannel::Original);
+        assert_eq!(
+            refund.funds_account,
+            FundsAccount::Unknown("NEW_ACCOUNT".to_string())
+        );
+        assert_eq!(refund.funds_account.to_string(), "NEW_ACCOUNT");
+    }
+}
@@ -0,0 +1,134 @@
+use crate::error::WechatApiError;
+use http::StatusCode;
+use rsa::rand_core::{OsRng, RngCore};
+use std::time::Duration;
+
+#[cfg(feature = "async")]
+use maybe_async::maybe_async as maybe_async_attr;
+#[cfg(not(feature = "async"))]
+use maybe_async::must_be_sync as maybe_async_attr;
+
+/// 重试策略，仅用于幂等的请求(查询、关单、相同商户退款单号的退款)
+///
+/// 第n次重试前等待`min(base_delay * 2^n, max_delay)`，开启jitter时在其一半到全部之间随机
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// 最大重试次数，0为不重试
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(5),
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// 不重试
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    pub fn with_base_delay(mut self, base_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self
+    }
+
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// 第attempt次重试(从0开始)前的等待时间
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        if !self.jitter || delay.is_zero() {
+            return delay;
+        }
+        let half = delay / 2;
+        let random = OsRng.next_u64() % (half.as_nanos() as u64 + 1);
+        half + Duration::from_nanos(random)
+    }
+}
+
+/// 可重试的应答：5xx、429以及`SYSTEM_ERROR`、`FREQUENCY_LIMITED`等错误码
+pub(crate) fn is_retryable_response(status: StatusCode, body: &str) -> bool {
+    !status.is_success() && WechatApiError::from_body(status, body.as_bytes()).is_retryable()
+}
+
+#[cfg(not(feature = "async"))]
+#[maybe_async_attr]
+pub(crate) async fn sleep(delay: Duration) {
+    std::thread::sleep(delay);
+}
+
+#[cfg(feature = "async")]
+#[maybe_async_attr]
+pub(crate) async fn sleep(delay: Duration) {
+    tokio::time::sleep(delay).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::retry::{is_retryable_response, RetryPolicy};
+    use http::StatusCode;
+    use std::time::Duration;
+
+    #[test]
+    fn test_retry_delay() {
+        let policy = RetryPolicy::default()
+            .with_base_delay(Duration::from_millis(100))
+            .with_max_delay(Duration::from_millis(350))
+            .with_jitter(false);
+        assert_eq!(policy.delay(0), Duration::from_millis(100));
+        assert_eq!(policy.delay(1), Duration::from_millis(200));
+        assert_eq!(policy.delay(2), Duration::from_millis(350));
+        assert_eq!(policy.delay(40), Duration::from_millis(350));
+
+        let policy = policy.with_jitter(true);
+        for attempt in 0..5 {
+            let delay = policy.delay(attempt);
+            let max = policy.clone().with_jitter(false).delay(attempt);
+            assert!(delay >= max / 2 && delay <= max);
+        }
+    }
+
+    #[test]
+    fn test_retryable_response() {
+        assert!(is_retryable_response(StatusCode::BAD_GATEWAY, ""));
+        assert!(is_retryable_response(StatusCode::TOO_MANY_REQUESTS, ""));
+        assert!(is_retryable_response(
+            StatusCode::FORBIDDEN,
+            r#"{"code":"FREQUENCY_LIMITED","message":"频率超限"}"#
+        ));
+        assert!(!is_retryable_response(
+            StatusCode::BAD_REQUEST,
+            r#"{"code":"PARAM_ERROR","message":"参数错误"}"#
+        ));
+        assert!(!is_retryable_response(StatusCode::OK, ""));
+    }
+}
@@ -0,0 +1,132 @@
+//! 请求参数中的敏感字段，序列化时使用平台公钥自动加密
+//!
+//! 字段标记`#[serde(serialize_with = "sensitive::encrypt")]`(`Option`字段使用`sensitive::encrypt_option`)，
+//! 再通过`sensitive::to_json`传入平台公钥序列化；未经`to_json`直接序列化时返回错误，避免敏感信息以明文上送
+//!
+//! ```rust,ignore
+//! #[derive(Serialize)]
+//! struct Params {
+//!     name: String,
+//!     #[serde(
+//!         serialize_with = "sensitive::encrypt_option",
+//!         skip_serializing_if = "Option::is_none"
+//!     )]
+//!     mobile: Option<String>,
+//! }
+//!
+//! let json = sensitive::to_json(&params, platform_key.as_str())?;
+//! ```
+use crate::error::PayError;
+use crate::sign;
+use serde::ser::Error;
+use serde::{Serialize, Serializer};
+use std::cell::RefCell;
+
+thread_local! {
+    /// 当前`to_json`调用传入的平台公钥，仅在序列化期间有效
+    static PLATFORM_KEY: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// 使用平台公钥(PEM)序列化请求参数，标记为敏感的字段加密后输出
+pub fn to_json<T: Serialize>(params: &T, platform_key: &str) -> Result<String, PayError> {
+    /// 序列化结束(包括panic)后恢复之前的平台公钥
+    struct Restore(Option<String>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            PLATFORM_KEY.with(|key| *key.borrow_mut() = previous);
+        }
+    }
+
+    let previous = PLATFORM_KEY.with(|key| key.replace(Some(platform_key.to_string())));
+    let _restore = Restore(previous);
+    serde_json::to_string(params).map_err(|e| PayError::EncryptError(e.to_string()))
+}
+
+/// serde`serialize_with`，使用`to_json`传入的平台公钥加密字段
+pub fn encrypt<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: AsRef<str> + ?Sized,
+    S: Serializer,
+{
+    let ciphertext = PLATFORM_KEY.with(|key| match key.borrow().as_deref() {
+        Some(platform_key) => sign::encrypt_sensitive(value.as_ref(), platform_key)
+            .map_err(|e| S::Error::custom(e.to_string())),
+        None => Err(S::Error::custom(
+            "sensitive field must be serialized with sensitive::to_json",
+        )),
+    })?;
+    serializer.serialize_str(ciphertext.as_str())
+}
+
+/// 同`encrypt`，用于`Option<String>`字段，None时输出null
+pub fn encrypt_option<S: Serializer>(
+    value: &Option<String>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match value {
+        Some(value) => encrypt(value, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::{AbnormalRefundParams, AbnormalRefundType};
+    use crate::sensitive;
+    use crate::sign;
+    use rsa::pkcs8::{EncodePrivateKey, EncodePublicKey, LineEnding};
+    use rsa::rand_core::OsRng;
+    use rsa::{RsaPrivateKey, RsaPublicKey};
+    use serde_json::Value;
+
+    #[test]
+    fn test_encrypt_sensitive_fields() {
+        let private_key = RsaPrivateKey::new(&mut OsRng, 1024).unwrap();
+        let public_key = RsaPublicKey::from(&private_key)
+            .to_public_key_pem(LineEnding::LF)
+            .unwrap();
+        let private_key = private_key.to_pkcs8_pem(LineEnding::LF).unwrap();
+        let params = AbnormalRefundParams::new(
+            "123456",
+            AbnormalRefundType::UserBankCard,
+            Some("ICBC_DEBIT"),
+            Some("6212000000000000000"),
+            Some("张三"),
+        );
+        let json = sensitive::to_json(&params, public_key.as_str()).unwrap();
+        let json: Value = serde_json::from_str(json.as_str()).unwrap();
+        assert_eq!(json["type"], "USER_BANK_CARD");
+        assert_eq!(json["bank_type"], "ICBC_DEBIT");
+        let real_name = json["real_name"].as_str().unwrap();
+        assert_ne!(real_name, "张三");
+        assert_eq!(
+            sign::decrypt_sensitive(real_name, private_key.as_str()).unwrap(),
+            "张三"
+        );
+        let bank_account = json["bank_account"].as_str().unwrap();
+        assert_eq!(
+            sign::decrypt_sensitive(bank_account, private_key.as_str()).unwrap(),
+            "6212000000000000000"
+        );
+
+        // 平台公钥无效或未传入平台公钥时返回错误，不会输出明文
+        assert!(sensitive::to_json(&params, "invalid").is_err());
+        assert!(serde_json::to_string(&params).is_err());
+
+        // 未上送的敏感字段不会出现在请求中
+        let params = AbnormalRefundParams::new(
+            "123456",
+            AbnormalRefundType::MerchantBankCard,
+            None,
+            None,
+            None,
+        );
+        let json = sensitive::to_json(&params, public_key.as_str()).unwrap();
+        let json: Value = serde_json::from_str(json.as_str()).unwrap();
+        assert_eq!(json["type"], "MERCHANT_BANK_CARD");
+        assert!(json.get("bank_account").is_none());
+        assert!(json.get("real_name").is_none());
+    }
+}
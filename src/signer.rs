@@ -0,0 +1,190 @@
+//! 请求签名，商户私钥可以保存在KMS、HSM或独立的签名服务中
+//!
+//! 默认使用内存中的商户私钥(`RsaSigner`)，可以通过`WechatPay::new_with_signer`或`WechatPay::with_signer`替换
+use crate::error::PayError;
+use crate::sign::MerchantPrivateKey;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+#[cfg(feature = "async")]
+use maybe_async::maybe_async as maybe_async_attr;
+#[cfg(not(feature = "async"))]
+use maybe_async::must_be_sync as maybe_async_attr;
+
+/// 商户签名，启用`async`时为异步trait，否则为同步trait
+#[maybe_async_attr]
+pub trait Signer: Debug + Send + Sync {
+    /// 使用商户私钥对message做SHA256 with RSA签名，返回base64编码的签名
+    async fn sign(&self, message: &str) -> Result<String, PayError>;
+
+    /// 使用商户私钥解密base64编码的RSA-OAEP(SHA-1)密文，用于应答中的敏感信息，默认不支持
+    async fn decrypt(&self, ciphertext: &str) -> Result<String, PayError> {
+        let _ = ciphertext;
+        Err(PayError::DecryptError(
+            "signer does not support decrypt".to_string(),
+        ))
+    }
+}
+
+#[maybe_async_attr]
+impl<T> Signer for Arc<T>
+where
+    T: Signer + ?Sized,
+{
+    async fn sign(&self, message: &str) -> Result<String, PayError> {
+        self.as_ref().sign(message).await
+    }
+
+    async fn decrypt(&self, ciphertext: &str) -> Result<String, PayError> {
+        self.as_ref().decrypt(ciphertext).await
+    }
+}
+
+/// 使用内存中的商户私钥签名，私钥只在创建时解析一次
+#[derive(Debug, Clone)]
+pub struct RsaSigner {
+    key: MerchantPrivateKey,
+}
+
+impl RsaSigner {
+    /// 支持PKCS#8与PKCS#1的PEM或DER格式
+    pub fn new(private_key: impl AsRef<[u8]>) -> Result<Self, PayError> {
+        Ok(Self {
+            key: MerchantPrivateKey::parse(private_key)?,
+        })
+    }
+}
+
+#[maybe_async_attr]
+impl Signer for RsaSigner {
+    async fn sign(&self, message: &str) -> Result<String, PayError> {
+        self.key.sha256_sign(message)
+    }
+
+    async fn decrypt(&self, ciphertext: &str) -> Result<String, PayError> {
+        self.key.decrypt_sensitive(ciphertext)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::async_impl::pay::tests::stub_wechat_pay;
+    use crate::error::PayError;
+    use crate::pay::{WechatPay, API_DOMAIN};
+    use crate::request::HttpMethod;
+    use crate::sign;
+    use crate::signer::Signer;
+    use crate::util;
+    use http::header::AUTHORIZATION;
+    use rsa::pkcs1::EncodeRsaPrivateKey;
+    use rsa::pkcs8::{EncodePublicKey, LineEnding};
+    use rsa::rand_core::OsRng;
+    use rsa::sha2::{Digest, Sha256};
+    use rsa::{Oaep, Pkcs1v15Sign, RsaPrivateKey, RsaPublicKey};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[cfg(feature = "async")]
+    use maybe_async::maybe_async as maybe_async_attr;
+    #[cfg(not(feature = "async"))]
+    use maybe_async::must_be_sync as maybe_async_attr;
+
+    /// 模拟KMS，私钥不离开签名器，记录签名次数
+    #[derive(Debug)]
+    struct FakeKms {
+        key: RsaPrivateKey,
+        calls: AtomicUsize,
+    }
+
+    #[maybe_async_attr]
+    impl Signer for FakeKms {
+        async fn sign(&self, message: &str) -> Result<String, PayError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let hashed = Sha256::new().chain_update(message).finalize();
+            let signature = self
+                .key
+                .sign(Pkcs1v15Sign::new::<Sha256>(), &hashed)
+                .map_err(|e| PayError::SignError(e.to_string()))?;
+            Ok(util::base64_encode(signature))
+        }
+    }
+
+    /// 从Authorization中取出签名串，使用商户公钥验签
+    fn assert_authorization(authorization: &str, public_key: &str) {
+        let field = |name: &str| {
+            authorization
+                .split(&format!("{}=\"", name))
+                .nth(1)
+                .and_then(|value| value.split('"').next())
+                .unwrap()
+                .to_string()
+        };
+        let message = format!(
+            "GET\n/v3/certificates\n{}\n{}\n\n",
+            field("timestamp"),
+            field("nonce_str")
+        );
+        sign::sha256_verify(public_key, message.as_str(), field("signature").as_str()).unwrap();
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_custom_signer() {
+        let platform_key = RsaPrivateKey::new(&mut OsRng, 1024).unwrap();
+        let key = RsaPrivateKey::new(&mut OsRng, 1024).unwrap();
+        let public_key = RsaPublicKey::from(&key)
+            .to_public_key_pem(LineEnding::LF)
+            .unwrap();
+        let kms = Arc::new(FakeKms {
+            key,
+            calls: AtomicUsize::new(0),
+        });
+        let wechat_pay = stub_wechat_pay(API_DOMAIN, &platform_key).with_signer(kms.clone());
+        let headers = wechat_pay
+            .build_header(HttpMethod::GET, "/v3/certificates", "")
+            .await
+            .unwrap();
+        assert_authorization(headers[AUTHORIZATION].to_str().unwrap(), &public_key);
+        let sign_data = wechat_pay
+            .mut_sign_data("prepay_id=", "wx201410272009395522657a690389285100")
+            .await
+            .unwrap();
+        assert_eq!(
+            sign_data.package,
+            "prepay_id=wx201410272009395522657a690389285100"
+        );
+        assert_eq!(kms.calls.load(Ordering::SeqCst), 2);
+        let result = wechat_pay.decrypt_sensitive("5byg5LiJ").await;
+        assert!(result.is_err());
+    }
+
+    /// 默认的RsaSigner签名与解密，支持PKCS#1私钥，私钥无效时返回错误
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    #[allow(deprecated)]
+    async fn test_rsa_signer() {
+        let platform_key = RsaPrivateKey::new(&mut OsRng, 1024).unwrap();
+        let key = RsaPrivateKey::new(&mut OsRng, 1024).unwrap();
+        let public = RsaPublicKey::from(&key);
+        let ciphertext = public
+            .encrypt(&mut OsRng, Oaep::new::<sha1::Sha1>(), "张三".as_bytes())
+            .unwrap();
+        let public_key = public.to_public_key_pem(LineEnding::LF).unwrap();
+        let wechat_pay = stub_wechat_pay(API_DOMAIN, &platform_key)
+            .with_private_key(key.to_pkcs1_pem(LineEnding::LF).unwrap().as_bytes())
+            .unwrap();
+        let signature = wechat_pay.rsa_sign("hello").await.unwrap();
+        sign::sha256_verify(public_key.as_str(), "hello", signature.as_str()).unwrap();
+        let plaintext = wechat_pay
+            .decrypt_sensitive(util::base64_encode(ciphertext))
+            .await
+            .unwrap();
+        assert_eq!(plaintext, "张三");
+
+        let wechat_pay = WechatPay::new("appid", "mch_id", "not a key", "serial", "v3", "notify");
+        let result = wechat_pay.rsa_sign("hello").await;
+        assert!(matches!(result, Err(PayError::SignError(_))));
+        let result = wechat_pay
+            .build_header(HttpMethod::GET, "/v3/certificates", "")
+            .await;
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,318 @@
+//! http传输层，SDK签名后的请求通过`HttpTransport`发送
+//!
+//! 默认使用reqwest(启用`async`时为异步client，否则为blocking client)，
+//! 可以通过`WechatPay::with_transport`替换为自定义实现，例如带双向TLS、代理或连接池的client
+use crate::error::PayError;
+use crate::request::HttpMethod;
+use http::{HeaderMap, StatusCode};
+use std::fmt::Debug;
+use std::sync::Arc;
+
+#[cfg(feature = "async")]
+use maybe_async::maybe_async as maybe_async_attr;
+#[cfg(not(feature = "async"))]
+use maybe_async::must_be_sync as maybe_async_attr;
+
+/// 已签名的http请求，url为完整地址
+#[derive(Debug, Clone)]
+pub struct HttpRequest {
+    pub method: HttpMethod,
+    pub url: String,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+/// http应答
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+/// http传输，启用`async`时为异步trait，否则为同步trait
+#[maybe_async_attr]
+pub trait HttpTransport: Debug + Send + Sync {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, PayError>;
+}
+
+#[maybe_async_attr]
+impl<T> HttpTransport for Arc<T>
+where
+    T: HttpTransport + ?Sized,
+{
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, PayError> {
+        self.as_ref().send(request).await
+    }
+}
+
+#[cfg(any(feature = "async", feature = "blocking"))]
+pub use self::reqwest_impl::{ReqwestTransport, ReqwestTransportBuilder};
+
+#[cfg(any(feature = "async", feature = "blocking"))]
+mod reqwest_impl {
+    use super::{HttpRequest, HttpResponse, HttpTransport};
+    use crate::error::PayError;
+    use crate::request::HttpMethod;
+    use std::sync::OnceLock;
+    use std::time::Duration;
+
+    #[cfg(not(feature = "async"))]
+    use reqwest::blocking::{Client, ClientBuilder};
+    #[cfg(feature = "async")]
+    use reqwest::{Client, ClientBuilder};
+
+    #[cfg(feature = "async")]
+    use maybe_async::maybe_async as maybe_async_attr;
+    #[cfg(not(feature = "async"))]
+    use maybe_async::must_be_sync as maybe_async_attr;
+
+    /// 基于reqwest的默认传输
+    ///
+    /// client在首次发送时创建：blocking client内部持有tokio运行时，提前创建会导致在异步上下文中drop时panic
+    #[derive(Debug, Clone, Default)]
+    pub struct ReqwestTransport {
+        options: ClientOptions,
+        client: OnceLock<Client>,
+    }
+
+    impl ReqwestTransport {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// 配置连接超时、读超时、代理、连接池与根证书
+        pub fn builder() -> ReqwestTransportBuilder {
+            ReqwestTransportBuilder::default()
+        }
+
+        /// 使用自定义的reqwest client
+        pub fn with_client(client: Client) -> Self {
+            Self {
+                options: ClientOptions::default(),
+                client: OnceLock::from(client),
+            }
+        }
+
+        fn client(&self) -> Result<&Client, PayError> {
+            if let Some(client) = self.client.get() {
+                return Ok(client);
+            }
+            let client = self.options.build()?;
+            Ok(self.client.get_or_init(|| client))
+        }
+    }
+
+    #[derive(Debug, Clone, Default)]
+    struct ClientOptions {
+        connect_timeout: Option<Duration>,
+        read_timeout: Option<Duration>,
+        proxy: Option<reqwest::Proxy>,
+        pool_max_idle_per_host: Option<usize>,
+        pool_idle_timeout: Option<Duration>,
+        root_certificates: Vec<reqwest::Certificate>,
+    }
+
+    impl ClientOptions {
+        fn build(&self) -> Result<Client, PayError> {
+            let mut builder = ClientBuilder::new();
+            if let Some(timeout) = self.connect_timeout {
+                builder = builder.connect_timeout(timeout);
+            }
+            if let Some(timeout) = self.read_timeout {
+                #[cfg(feature = "async")]
+                {
+                    builder = builder.read_timeout(timeout);
+                }
+                #[cfg(not(feature = "async"))]
+                {
+                    builder = builder.timeout(timeout);
+                }
+            }
+            if let Some(proxy) = self.proxy.clone() {
+                builder = builder.proxy(proxy);
+            }
+            if let Some(max) = self.pool_max_idle_per_host {
+                builder = builder.pool_max_idle_per_host(max);
+            }
+            if let Some(timeout) = self.pool_idle_timeout {
+                builder = builder.pool_idle_timeout(timeout);
+            }
+            for certificate in &self.root_certificates {
+                builder = builder.add_root_certificate(certificate.clone());
+            }
+            Ok(builder.build()?)
+        }
+    }
+
+    #[derive(Debug, Default)]
+    pub struct ReqwestTransportBuilder {
+        options: ClientOptions,
+        root_certificates: Vec<Vec<u8>>,
+    }
+
+    impl ReqwestTransportBuilder {
+        pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+            self.options.connect_timeout = Some(timeout);
+            self
+        }
+
+        /// 读超时，blocking client为单次读写的超时
+        pub fn read_timeout(mut self, timeout: Duration) -> Self {
+            self.options.read_timeout = Some(timeout);
+            self
+        }
+
+        pub fn proxy(mut self, proxy: reqwest::Proxy) -> Self {
+            self.options.proxy = Some(proxy);
+            self
+        }
+
+        /// 每个host保持的最大空闲连接数
+        pub fn pool_max_idle_per_host(mut self, max: usize) -> Self {
+            self.options.pool_max_idle_per_host = Some(max);
+            self
+        }
+
+        pub fn pool_idle_timeout(mut self, timeout: Duration) -> Self {
+            self.options.pool_idle_timeout = Some(timeout);
+            self
+        }
+
+        /// 添加信任的根证书(PEM)，例如经过企业代理时
+        pub fn add_root_certificate(mut self, pem: impl AsRef<[u8]>) -> Self {
+            self.root_certificates.push(pem.as_ref().to_vec());
+            self
+        }
+
+        /// 校验根证书，client在首次发送时创建
+        pub fn build(self) -> Result<ReqwestTransport, PayError> {
+            let mut options = self.options;
+            for pem in self.root_certificates {
+                options
+                    .root_certificates
+                    .push(reqwest::Certificate::from_pem(&pem)?);
+            }
+            Ok(ReqwestTransport {
+                options,
+                client: OnceLock::new(),
+            })
+        }
+    }
+
+    #[maybe_async_attr]
+    impl HttpTransport for ReqwestTransport {
+        async fn send(&self, request: HttpRequest) -> Result<HttpResponse, PayError> {
+            let url = request.url;
+            let builder = match request.method {
+                HttpMethod::GET => self.client()?.get(url),
+                HttpMethod::POST => self.client()?.post(url),
+                HttpMethod::PUT => self.client()?.put(url),
+                HttpMethod::DELETE => self.client()?.delete(url),
+                HttpMethod::PATCH => self.client()?.patch(url),
+            };
+            let response = builder
+                .headers(request.headers)
+                .body(request.body)
+                .send()
+                .await?;
+            let status = response.status();
+            let headers = response.headers().clone();
+            let body = response.bytes().await?.to_vec();
+            Ok(HttpResponse {
+                status,
+                headers,
+                body,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::async_impl::pay::tests::stub_wechat_pay;
+    use crate::error::PayError;
+    use crate::pay::API_DOMAIN;
+    use crate::request::HttpMethod;
+    use crate::transport::{HttpRequest, HttpResponse, HttpTransport};
+    use http::header::{AUTHORIZATION, USER_AGENT};
+    use http::{HeaderMap, StatusCode};
+    use rsa::rand_core::OsRng;
+    use rsa::RsaPrivateKey;
+    use std::sync::{Arc, Mutex};
+
+    #[cfg(feature = "async")]
+    use maybe_async::maybe_async as maybe_async_attr;
+    #[cfg(not(feature = "async"))]
+    use maybe_async::must_be_sync as maybe_async_attr;
+
+    /// 内存传输，记录请求并按顺序返回预设应答
+    #[derive(Debug, Default)]
+    struct MemoryTransport {
+        requests: Mutex<Vec<HttpRequest>>,
+        responses: Mutex<Vec<HttpResponse>>,
+    }
+
+    #[maybe_async_attr]
+    impl HttpTransport for MemoryTransport {
+        async fn send(&self, request: HttpRequest) -> Result<HttpResponse, PayError> {
+            self.requests.lock().unwrap().push(request);
+            Ok(self.responses.lock().unwrap().remove(0))
+        }
+    }
+
+    #[maybe_async::test(not(feature = "async"), async(feature = "async", tokio::test))]
+    async fn test_memory_transport() {
+        let transport = Arc::new(MemoryTransport::default());
+        transport.responses.lock().unwrap().push(HttpResponse {
+            status: StatusCode::NO_CONTENT,
+            headers: HeaderMap::new(),
+            body: vec![],
+        });
+        let platform_key = RsaPrivateKey::new(&mut OsRng, 1024).unwrap();
+        let wechat_pay = stub_wechat_pay(API_DOMAIN, &platform_key)
+            .with_transport(transport.clone())
+            .without_response_verification();
+        wechat_pay.close_order("1243243").await.unwrap();
+        let requests = transport.requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        let request = &requests[0];
+        assert_eq!(request.method, HttpMethod::POST);
+        assert_eq!(
+            request.url,
+            "https://api.mch.weixin.qq.com/v3/pay/transactions/out-trade-no/1243243/close"
+        );
+        assert_eq!(
+            request.headers[USER_AGENT].to_str().unwrap(),
+            format!(
+                "wechat-pay-rust-sdk/{} ({})",
+                env!("CARGO_PKG_VERSION"),
+                std::env::consts::OS
+            )
+        );
+        let authorization = request.headers[AUTHORIZATION].to_str().unwrap();
+        assert!(authorization.starts_with("WECHATPAY2-SHA256-RSA2048 mchid=\"1900000100\""));
+        assert_eq!(request.body, br#"{"mchid":"1900000100"}"#);
+    }
+
+    #[test]
+    #[cfg(any(feature = "async", feature = "blocking"))]
+    fn test_reqwest_transport_builder() {
+        use crate::transport::ReqwestTransport;
+        use std::time::Duration;
+
+        let certificate = include_str!("../tests/fixtures/platform_cert.pem");
+        ReqwestTransport::builder()
+            .connect_timeout(Duration::from_secs(5))
+            .read_timeout(Duration::from_secs(10))
+            .proxy(reqwest::Proxy::all("http://127.0.0.1:8080").unwrap())
+            .pool_max_idle_per_host(16)
+            .add_root_certificate(certificate)
+            .build()
+            .unwrap();
+        assert!(ReqwestTransport::builder()
+            .add_root_certificate("not a certificate")
+            .build()
+            .is_err());
+    }
+}
@@ -38,6 +38,23 @@ pub fn x509_to_pem(content: &[u8]) -> Result<String, Box<dyn Error>> {
     ))
 }
 
+/// 证书序列号，大写十六进制
+pub fn x509_serial_no(content: &[u8]) -> Result<String, Box<dyn Error>> {
+    let pem = pem::parse(content)?;
+    let (_, cert) = x509_parser::parse_x509_certificate(pem.contents())?;
+    Ok(cert.serial.to_str_radix(16).to_uppercase())
+}
+
+/// 证书有效期(秒)，返回(生效时间, 过期时间)
+pub fn x509_validity(content: &[u8]) -> Result<(i64, i64), Box<dyn Error>> {
+    let pem = pem::parse(content)?;
+    let (_, cert) = x509_parser::parse_x509_certificate(pem.contents())?;
+    Ok((
+        cert.validity.not_before.timestamp(),
+        cert.validity.not_after.timestamp(),
+    ))
+}
+
 pub fn x509_is_valid(content: &[u8]) -> Result<(bool, i64), Box<dyn Error>> {
     let pem = pem::parse(content)?;
     let (_, cert) = x509_parser::parse_x509_certificate(pem.contents())?;
@@ -0,0 +1,56 @@
+use crate::error::PayError;
+use crate::pay::{WECHATPAY_NONCE, WECHATPAY_SERIAL, WECHATPAY_SIGNATURE, WECHATPAY_TIMESTAMP};
+use crate::sign;
+use http::header::HeaderMap;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+/// 接口应答验签，message为`应答时间戳\n应答随机串\n应答报文主体\n`
+pub trait ResponseVerifier: Debug + Send + Sync {
+    fn verify(&self, serial_no: &str, message: &str, signature: &str) -> Result<(), PayError>;
+}
+
+/// 平台证书序列号 -> 平台公钥(PEM)
+impl ResponseVerifier for HashMap<String, String> {
+    fn verify(&self, serial_no: &str, message: &str, signature: &str) -> Result<(), PayError> {
+        let pub_key = self.get(serial_no).ok_or_else(|| {
+            PayError::SignatureError(format!("platform pub key {} not found", serial_no))
+        })?;
+        sign::sha256_verify(pub_key.as_str(), message, signature)
+    }
+}
+
+impl<T> ResponseVerifier for Arc<T>
+where
+    T: ResponseVerifier + ?Sized,
+{
+    fn verify(&self, serial_no: &str, message: &str, signature: &str) -> Result<(), PayError> {
+        self.as_ref().verify(serial_no, message, signature)
+    }
+}
+
+/// 读取应答的`Wechatpay-*`头并验签
+pub(crate) fn verify_response_headers(
+    verifier: &dyn ResponseVerifier,
+    headers: &HeaderMap,
+    body: &str,
+) -> Result<(), PayError> {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .ok_or_else(|| PayError::SignatureError(format!("missing header {}", name)))
+    };
+    let serial_no = header(WECHATPAY_SERIAL)?;
+    let timestamp = header(WECHATPAY_TIMESTAMP)?;
+    let nonce = header(WECHATPAY_NONCE)?;
+    let signature = header(WECHATPAY_SIGNATURE)?;
+    let message = format!("{}\n{}\n{}\n", timestamp, nonce, body);
+    verifier
+        .verify(serial_no, message.as_str(), signature)
+        .map_err(|e| match e {
+            PayError::SignatureError(_) => e,
+            e => PayError::SignatureError(e.to_string()),
+        })
+}